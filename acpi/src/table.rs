@@ -3,6 +3,7 @@ mod gas;
 mod header;
 mod madt;
 mod rsdp;
+mod spcr;
 mod xsdt;
 
 pub use gas::GenericAddress;
@@ -14,6 +15,8 @@ pub use madt::Madt;
 pub use madt::MadtEntryHeader;
 pub use madt::MadtIterator;
 pub use rsdp::Rsdp;
+pub use spcr::Spcr;
+pub use xsdt::CorruptTable;
 pub use xsdt::Xsdt;
 
 use core::mem;
@@ -23,6 +26,7 @@ pub type Signature = &'static [u8; 4];
 pub const XSDT_SIG: Signature = b"XSDT";
 pub const FADT_SIG: Signature = b"FACP";
 pub const MADT_SIG: Signature = b"APIC";
+pub const SPCR_SIG: Signature = b"SPCR";
 
 pub trait AcpiTable: Sized {
     const SIG: &'static [u8; 4];
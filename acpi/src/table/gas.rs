@@ -6,3 +6,8 @@ pub struct GenericAddress {
     pub access_size: u8,
     pub address: u64,
 }
+
+impl GenericAddress {
+    pub const SYSTEM_MEMORY: u8 = 0;
+    pub const SYSTEM_IO: u8 = 1;
+}
@@ -0,0 +1,62 @@
+use super::AcpiHeader;
+use super::AcpiTable;
+use super::GenericAddress;
+use super::SPCR_SIG;
+use super::Signature;
+
+/// Serial Port Console Redirection, revision 2 layout
+#[repr(C, packed)]
+pub struct Spcr {
+    pub header: AcpiHeader,
+    pub interface_type: u8,
+    pub reserved0: [u8; 3],
+    pub base_address: GenericAddress,
+    pub interrupt_type: u8,
+    pub irq: u8,
+    pub gsi: u32,
+    pub configured_baud_rate: u8,
+    pub parity: u8,
+    pub stop_bits: u8,
+    pub flow_control: u8,
+    pub terminal_type: u8,
+    pub language: u8,
+    pub pci_device_id: u16,
+    pub pci_vendor_id: u16,
+    pub pci_bus: u8,
+    pub pci_device: u8,
+    pub pci_function: u8,
+    pub pci_flags: u32,
+    pub pci_segment: u8,
+    pub reserved1: u32,
+}
+
+impl Spcr {
+    pub const INTERFACE_16550: u8 = 0;
+    pub const INTERFACE_16450: u8 = 1;
+    pub const INTERFACE_16550_GAS: u8 = 0x12;
+}
+
+impl Spcr {
+    /// Whether the port is a 16550 compatible UART
+    pub fn is_16550(&self) -> bool {
+        matches!(
+            self.interface_type,
+            Self::INTERFACE_16550 | Self::INTERFACE_16450 | Self::INTERFACE_16550_GAS
+        )
+    }
+
+    /// None means the firmware leaves the UART as it is
+    pub fn baud_rate(&self) -> Option<u32> {
+        match self.configured_baud_rate {
+            3 => Some(9600),
+            4 => Some(19200),
+            6 => Some(57600),
+            7 => Some(115200),
+            _ => None,
+        }
+    }
+}
+
+impl AcpiTable for Spcr {
+    const SIG: Signature = SPCR_SIG;
+}
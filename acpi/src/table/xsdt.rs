@@ -9,6 +9,10 @@ pub struct Xsdt {
     pub header: AcpiHeader,
 }
 
+/// A table whose checksum doesn't add up, with its signature
+#[derive(Debug)]
+pub struct CorruptTable(pub Signature);
+
 pub struct XsdtIter<'a> {
     xsdt: &'a Xsdt,
     index: usize,
@@ -41,6 +45,14 @@ impl Xsdt {
         }
     }
 
+    /// For optional tables, a corrupt one is left to the caller to ignore or not
+    pub fn find<T: AcpiTable>(&self) -> Result<Option<&T>, CorruptTable> {
+        match self.entries().find(|entry| &entry.sig == T::SIG) {
+            Some(header) => header.getas().map(Some).ok_or(CorruptTable(T::SIG)),
+            None => Ok(None),
+        }
+    }
+
     pub fn find_unique<T: AcpiTable>(&self) -> &T {
         let Some(header) = self.entries().find(|entry| &entry.sig == T::SIG) else {
             if let Some(str_sig) = T::SIG.as_ascii() {
@@ -0,0 +1,10 @@
+use x64::mem::addr::PhysAddr;
+
+/// Entry points to the ACPI tables, already verified by the bootloader.
+/// ACPI memory and the RSDP are mapped at [crate::kaslr::KaslrInfo::offset_mapping].
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AcpiInfo {
+    pub rsdp: PhysAddr,
+    pub xsdt: PhysAddr,
+}
@@ -0,0 +1,234 @@
+//! Ring buffer of the bootloader's log records, for the kernel to replay.
+//!
+//! The state lives in front of the records, in the same memory, so the bootloader can keep
+//! logging after handing it over in [crate::BootInfo], up until it cedes control.
+//! Records never wrap around the end of the ring, the space left there is skipped instead.
+
+#[cfg(test)]
+mod test;
+
+use core::mem;
+use core::str;
+use log::Level;
+
+/// Size of the whole log, state included
+pub const BOOT_LOG_SIZE: usize = 64 * 1024;
+/// Longer messages are truncated
+pub const MAX_MESSAGE_LEN: usize = 1024;
+pub const MAX_TARGET_LEN: usize = u8::MAX as usize;
+
+/// size: u16, level: u8, target length: u8, timestamp: u64
+const RECORD_HEADER_SIZE: usize = 12;
+
+#[repr(C)]
+struct BootLogHeader {
+    /// Bytes ever written and given back, the ring holds `head - tail` bytes
+    head: u64,
+    tail: u64,
+    /// Records overwritten by newer ones
+    dropped: u64,
+    capacity: u64,
+}
+
+/// Read-only view of a log left by [BootLogWriter]
+#[derive(Clone, Copy)]
+pub struct BootLog<'a> {
+    header: &'a BootLogHeader,
+    ring: &'a [u8],
+}
+
+pub struct BootLogWriter<'a> {
+    header: &'a mut BootLogHeader,
+    ring: &'a mut [u8],
+}
+
+pub struct BootLogRecord<'a> {
+    pub level: Level,
+    /// TSC ticks since the bootloader started
+    pub timestamp: u64,
+    pub target: &'a str,
+    pub message: &'a str,
+}
+
+pub struct BootLogIter<'a> {
+    log: BootLog<'a>,
+    position: u64,
+}
+
+impl<'a> BootLog<'a> {
+    /// Returns None if `memory` does not hold a consistent log
+    pub fn open(memory: &'a [u8]) -> Option<Self> {
+        if !memory.as_ptr().cast::<BootLogHeader>().is_aligned()
+            || memory.len() < mem::size_of::<BootLogHeader>()
+        {
+            return None;
+        }
+        let (header, ring) = memory.split_at(mem::size_of::<BootLogHeader>());
+        let header = unsafe {
+            // SAFETY: Aligned and big enough, checked above
+            &*header.as_ptr().cast::<BootLogHeader>()
+        };
+        if header.capacity != ring.len() as u64
+            || header.tail > header.head
+            || header.head - header.tail > header.capacity
+        {
+            return None;
+        }
+        Some(Self { header, ring })
+    }
+
+    #[inline]
+    pub fn dropped(&self) -> u64 {
+        self.header.dropped
+    }
+
+    /// Oldest first
+    pub fn records(&self) -> BootLogIter<'a> {
+        BootLogIter {
+            log: *self,
+            position: self.header.tail,
+        }
+    }
+}
+
+impl<'a> BootLogWriter<'a> {
+    /// Starts an empty log in `memory`, which must be 8 bytes aligned
+    pub fn new(memory: &'a mut [u8]) -> Option<Self> {
+        if !memory.as_ptr().cast::<BootLogHeader>().is_aligned()
+            || memory.len() < mem::size_of::<BootLogHeader>() + RECORD_HEADER_SIZE
+        {
+            return None;
+        }
+        let (header, ring) = memory.split_at_mut(mem::size_of::<BootLogHeader>());
+        let header = unsafe {
+            // SAFETY: Aligned and big enough, checked above
+            &mut *header.as_mut_ptr().cast::<BootLogHeader>()
+        };
+        *header = BootLogHeader {
+            head: 0,
+            tail: 0,
+            dropped: 0,
+            capacity: ring.len() as u64,
+        };
+        Some(Self { header, ring })
+    }
+
+    /// Evicts the oldest records if there is not enough room.
+    /// `target` and `message` are truncated to [MAX_TARGET_LEN] and [MAX_MESSAGE_LEN].
+    pub fn push(&mut self, level: Level, timestamp: u64, target: &str, message: &str) {
+        let target = truncate(target, MAX_TARGET_LEN);
+        let message = truncate(message, MAX_MESSAGE_LEN);
+        let size = RECORD_HEADER_SIZE + target.len() + message.len();
+        let capacity = self.ring.len();
+        if size > capacity {
+            return;
+        }
+        let offset = (self.header.head % capacity as u64) as usize;
+        let start = if capacity - offset < size {
+            self.header.head + (capacity - offset) as u64
+        } else {
+            self.header.head
+        };
+        let end = start + size as u64;
+        while end - self.header.tail > capacity as u64 {
+            let log = BootLog {
+                header: self.header,
+                ring: self.ring,
+            };
+            let (next, record) = log.next_record(self.header.tail);
+            if record.is_some() {
+                self.header.dropped += 1;
+            }
+            self.header.tail = next;
+        }
+        if start != self.header.head && capacity - offset >= 2 {
+            // Padding marker, see `next_record`
+            self.ring[offset..offset + 2].copy_from_slice(&0u16.to_le_bytes());
+        }
+
+        let record = &mut self.ring[(start % capacity as u64) as usize..][..size];
+        let (record_header, contents) = record.split_at_mut(RECORD_HEADER_SIZE);
+        record_header[0..2].copy_from_slice(&(size as u16).to_le_bytes());
+        record_header[2] = level as u8;
+        record_header[3] = target.len() as u8;
+        record_header[4..12].copy_from_slice(&timestamp.to_le_bytes());
+        contents[..target.len()].copy_from_slice(target.as_bytes());
+        contents[target.len()..].copy_from_slice(message.as_bytes());
+        self.header.head = end;
+    }
+
+    pub fn as_log(&self) -> BootLog<'_> {
+        BootLog {
+            header: self.header,
+            ring: self.ring,
+        }
+    }
+}
+
+impl<'a> BootLog<'a> {
+    /// Position of the record after the one at `position`, and the record itself,
+    /// which is None when skipping the end of the ring
+    fn next_record(&self, position: u64) -> (u64, Option<BootLogRecord<'a>>) {
+        let capacity = self.ring.len();
+        let offset = (position % capacity as u64) as usize;
+        let skip_end = position + (capacity - offset) as u64;
+        let Some(header) = self.ring[offset..].get(..RECORD_HEADER_SIZE) else {
+            return (skip_end, None);
+        };
+        let size = u16::from_le_bytes([header[0], header[1]]) as usize;
+        if size == 0 {
+            return (skip_end, None);
+        }
+        let target_len = header[3] as usize;
+        let record = self.ring[offset..]
+            .get(RECORD_HEADER_SIZE..size)
+            .filter(|contents| contents.len() >= target_len);
+        let level = match header[2] {
+            1 => Some(Level::Error),
+            2 => Some(Level::Warn),
+            3 => Some(Level::Info),
+            4 => Some(Level::Debug),
+            5 => Some(Level::Trace),
+            _ => None,
+        };
+        let (Some(record), Some(level)) = (record, level) else {
+            // Corrupt, give up on the rest
+            return (self.header.head, None);
+        };
+        let (target, message) = record.split_at(target_len);
+        let record = BootLogRecord {
+            level,
+            timestamp: u64::from_le_bytes(header[4..12].try_into().unwrap()),
+            target: str::from_utf8(target).unwrap_or("?"),
+            message: str::from_utf8(message).unwrap_or("?"),
+        };
+        (position + size as u64, Some(record))
+    }
+}
+
+impl<'a> Iterator for BootLogIter<'a> {
+    type Item = BootLogRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.log.header.head {
+            let (next, record) = self.log.next_record(self.position);
+            self.position = next;
+            if record.is_some() {
+                return record;
+            }
+        }
+        None
+    }
+}
+
+/// Longest prefix of `s` of at most `len` bytes, on a character boundary
+fn truncate(s: &str, len: usize) -> &str {
+    if s.len() <= len {
+        return s;
+    }
+    let mut end = len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
@@ -0,0 +1,89 @@
+use super::BootLog;
+use super::BootLogWriter;
+use super::MAX_MESSAGE_LEN;
+use super::RECORD_HEADER_SIZE;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use log::Level;
+
+/// 8 bytes aligned memory for a log with `capacity` bytes of records
+fn memory(capacity: usize) -> &'static mut [u8] {
+    let words = vec![0u64; (32 + capacity).div_ceil(8)].leak();
+    unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr().cast(), 32 + capacity) }
+}
+
+fn messages(log: &BootLog) -> Vec<String> {
+    log.records()
+        .map(|record| String::from(record.message))
+        .collect()
+}
+
+#[test]
+fn test_push_and_read() {
+    let memory = memory(256);
+    let mut writer = BootLogWriter::new(memory).unwrap();
+    writer.push(Level::Info, 10, "boot", "hello");
+    writer.push(Level::Warn, 20, "boot::acpi", "world");
+    let log = writer.as_log();
+    let records: Vec<_> = log.records().collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].level, Level::Info);
+    assert_eq!(records[0].timestamp, 10);
+    assert_eq!(records[0].target, "boot");
+    assert_eq!(records[1].level, Level::Warn);
+    assert_eq!(records[1].target, "boot::acpi");
+    assert_eq!(records[1].message, "world");
+    assert_eq!(log.dropped(), 0);
+}
+
+#[test]
+fn test_open() {
+    let memory = memory(256);
+    let address = memory.as_ptr();
+    let mut writer = BootLogWriter::new(memory).unwrap();
+    writer.push(Level::Error, 0, "", "kept");
+    let memory = unsafe { core::slice::from_raw_parts(address, 32 + 256) };
+    let log = BootLog::open(memory).unwrap();
+    assert_eq!(messages(&log), ["kept"]);
+    assert!(BootLog::open(&memory[..100]).is_none());
+    assert!(BootLog::open(&memory[1..]).is_none());
+}
+
+#[test]
+fn test_wrap_around() {
+    // Room for 3 records of 20 bytes, with 4 bytes left at the end
+    let memory = memory(64);
+    let mut writer = BootLogWriter::new(memory).unwrap();
+    for i in 0..10 {
+        let message = format!("msg{i}{}", "x".repeat(20 - RECORD_HEADER_SIZE - 4));
+        writer.push(Level::Debug, i, "", &message);
+        let log = writer.as_log();
+        let messages = messages(&log);
+        assert_eq!(messages.last(), Some(&message));
+        assert!(messages.len() <= 3);
+    }
+    let log = writer.as_log();
+    assert_eq!(
+        log.records()
+            .map(|record| record.timestamp)
+            .collect::<Vec<_>>(),
+        [7, 8, 9]
+    );
+    assert_eq!(log.dropped(), 7);
+}
+
+#[test]
+fn test_truncate() {
+    let memory = memory(4096);
+    let mut writer = BootLogWriter::new(memory).unwrap();
+    let long = "é".repeat(MAX_MESSAGE_LEN);
+    writer.push(Level::Trace, 0, "", &long);
+    writer.push(Level::Trace, 0, "", &"x".repeat(5000));
+    let log = writer.as_log();
+    let messages = messages(&log);
+    assert_eq!(messages[0].len(), MAX_MESSAGE_LEN);
+    assert!(messages[0].chars().all(|c| c == 'é'));
+    assert_eq!(messages[1].len(), MAX_MESSAGE_LEN);
+}
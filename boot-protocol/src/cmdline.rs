@@ -0,0 +1,48 @@
+use core::str;
+
+pub const MAX_CMDLINE_LEN: usize = 2048;
+
+/// Free-form kernel command line, from the boot configuration
+#[repr(C)]
+pub struct CmdLine {
+    bytes: [u8; MAX_CMDLINE_LEN],
+    len: usize,
+}
+
+impl CmdLine {
+    pub const fn empty() -> Self {
+        Self {
+            bytes: [0; MAX_CMDLINE_LEN],
+            len: 0,
+        }
+    }
+    /// Returns `None` if `cmdline` is longer than [MAX_CMDLINE_LEN] bytes
+    pub fn new(cmdline: &str) -> Option<Self> {
+        let mut this = Self::empty();
+        this.bytes
+            .get_mut(..cmdline.len())?
+            .copy_from_slice(cmdline.as_bytes());
+        this.len = cmdline.len();
+        Some(this)
+    }
+}
+
+impl CmdLine {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.bytes
+            .get(..self.len)
+            .is_some_and(|bytes| str::from_utf8(bytes).is_ok())
+    }
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            // SAFETY: Copied from a str in `new`
+            str::from_utf8_unchecked(&self.bytes[..self.len])
+        }
+    }
+}
+
+impl Default for CmdLine {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
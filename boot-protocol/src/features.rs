@@ -1,3 +1,7 @@
+use crate::ffi::is_valid_bool;
+use crate::ffi::raw_discriminant;
+
+#[repr(C)]
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct FeatureSet {
     pub vendor: Vendor,
@@ -8,6 +12,12 @@ pub struct FeatureSet {
     pub pk_super: bool,
 }
 
+/// Optional CPU features, as bits. Mandatory ones are checked by the bootloader anyway.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureMask(u64);
+
+#[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Vendor {
     GenuineIntel,
@@ -25,3 +35,83 @@ impl TryFrom<[u8; 12]> for Vendor {
         }
     }
 }
+
+impl FeatureSet {
+    /// Whether the vendor and flags hold valid values
+    pub(crate) fn is_valid(&self) -> bool {
+        let vendor = unsafe {
+            // SAFETY: Read as an integer since it may be anything
+            raw_discriminant(&raw const self.vendor)
+        };
+        let flags = [
+            &raw const self.context_id,
+            &raw const self.inv_context_id,
+            &raw const self.shadow_stack,
+            &raw const self.pk_user,
+            &raw const self.pk_super,
+        ];
+        vendor <= Vendor::AuthenticAMD as u32
+            && flags.into_iter().all(|flag| unsafe {
+                // SAFETY: Fields of self
+                is_valid_bool(flag)
+            })
+    }
+    pub const fn mask(&self) -> FeatureMask {
+        let mut mask = FeatureMask::NONE;
+        if self.context_id {
+            mask = mask.with(FeatureMask::CONTEXT_ID);
+        }
+        if self.inv_context_id {
+            mask = mask.with(FeatureMask::INV_CONTEXT_ID);
+        }
+        if self.shadow_stack {
+            mask = mask.with(FeatureMask::SHADOW_STACK);
+        }
+        if self.pk_user {
+            mask = mask.with(FeatureMask::PK_USER);
+        }
+        if self.pk_super {
+            mask = mask.with(FeatureMask::PK_SUPER);
+        }
+        mask
+    }
+}
+
+impl FeatureMask {
+    pub const NONE: Self = Self(0);
+    pub const CONTEXT_ID: Self = Self(1 << 0);
+    pub const INV_CONTEXT_ID: Self = Self(1 << 1);
+    pub const SHADOW_STACK: Self = Self(1 << 2);
+    pub const PK_USER: Self = Self(1 << 3);
+    pub const PK_SUPER: Self = Self(1 << 4);
+
+    const NAMES: [(Self, &str); 5] = [
+        (Self::CONTEXT_ID, "PCID"),
+        (Self::INV_CONTEXT_ID, "INVPCID"),
+        (Self::SHADOW_STACK, "CET shadow stack"),
+        (Self::PK_USER, "PKU"),
+        (Self::PK_SUPER, "PKS"),
+    ];
+}
+
+impl FeatureMask {
+    #[inline]
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    #[inline]
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    /// Names of the features in the mask
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(feature, _)| feature.0 & self.0 != 0)
+            .map(|(_, name)| name)
+    }
+}
@@ -0,0 +1,81 @@
+use core::slice;
+
+/// Discriminant of the fieldless or `repr(C, u32)` enum at `field`, which may be invalid
+///
+/// # Safety
+/// `field` must be readable for 4 bytes
+pub(crate) unsafe fn raw_discriminant<T>(field: *const T) -> u32 {
+    unsafe {
+        // SAFETY: Guaranteed by the caller, any u32 is valid
+        field.cast::<u32>().read()
+    }
+}
+
+/// Whether the bool at `field` is 0 or 1
+///
+/// # Safety
+/// `field` must be readable
+pub(crate) unsafe fn is_valid_bool(field: *const bool) -> bool {
+    unsafe {
+        // SAFETY: Guaranteed by the caller, any u8 is valid
+        field.cast::<u8>().read() <= 1
+    }
+}
+
+/// `&'static mut [T]` with a stable layout
+#[repr(C)]
+pub struct RawSlice<T> {
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<T> RawSlice<T> {
+    pub fn new(slice: &'static mut [T]) -> Self {
+        Self {
+            ptr: slice.as_mut_ptr(),
+            len: slice.len(),
+        }
+    }
+}
+
+impl<T> RawSlice<T> {
+    /// Whether this could be a slice: aligned, not null unless empty,
+    /// and not wrapping around the address space
+    pub(crate) fn is_valid(&self) -> bool {
+        let Some(size) = self.len.checked_mul(size_of::<T>()) else {
+            return false;
+        };
+        self.ptr.is_aligned()
+            && (self.len == 0 || !self.ptr.is_null())
+            && size <= isize::MAX as usize
+            && (self.ptr as usize).checked_add(size).is_some()
+    }
+    #[inline]
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// # Safety
+    /// The memory must still be mapped the way the bootloader left it
+    pub unsafe fn as_slice(&self) -> &[T] {
+        unsafe {
+            // SAFETY: Guaranteed by the caller
+            slice::from_raw_parts(self.ptr, self.len)
+        }
+    }
+    /// # Safety
+    /// The memory must still be mapped the way the bootloader left it
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            // SAFETY: Guaranteed by the caller
+            slice::from_raw_parts_mut(self.ptr, self.len)
+        }
+    }
+}
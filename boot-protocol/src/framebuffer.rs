@@ -1,8 +1,45 @@
+use crate::ffi::RawSlice;
+use crate::ffi::raw_discriminant;
+use x64::framebuffer::PixelMode;
+use x64::mem::addr::PhysAddr;
+
+/// One per GOP output, the first one being the firmware's primary output
+pub const MAX_FRAMEBUFFER_COUNT: usize = 4;
+
 #[repr(C)]
 pub struct FramebufferInfo {
-    pub fb: &'static mut [u32],
+    /// Where `fb` lives in physical memory
+    pub phys: PhysAddr,
+    pub fb: RawSlice<u8>,
     pub width: usize,
     pub height: usize,
+    /// In pixels
     pub stride: usize,
-    pub buffer: &'static mut [u32],
+    pub mode: PixelMode,
+    pub bytes_per_pixel: usize,
+    /// Same size as `fb`, in normal memory
+    pub buffer: RawSlice<u8>,
+}
+
+impl FramebufferInfo {
+    /// Whether the mode and its pixel size are valid, and every pixel is in both buffers
+    pub(crate) fn is_valid(&self) -> bool {
+        let mode = unsafe {
+            // SAFETY: Read as an integer since it may be anything
+            raw_discriminant(&raw const self.mode)
+        };
+        // RgbRs, BgrRs or Bitmask
+        mode <= 2
+            && self.bytes_per_pixel != 0
+            && self.bytes_per_pixel == self.mode.bytes_per_pixel()
+            && self.fb.is_valid()
+            && self.buffer.is_valid()
+            && self.buffer.len() == self.fb.len()
+            && self.width <= self.stride
+            && self
+                .stride
+                .checked_mul(self.height)
+                .and_then(|pixels| pixels.checked_mul(self.bytes_per_pixel))
+                .is_some_and(|size| size <= self.fb.len())
+    }
 }
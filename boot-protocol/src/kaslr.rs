@@ -0,0 +1,12 @@
+/// Where the bootloader placed the kernel and the offset mapping,
+/// randomized unless disabled in the bootloader configuration
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KaslrInfo {
+    /// Added to every link time address of the kernel,
+    /// which is linked at the start of [config::vmem::KBIN_REGION]
+    pub kernel_slide: usize,
+    /// RAM and ACPI memory are mapped at their physical address plus this,
+    /// inside [config::vmem::PHYSICAL_MAPPING_REGION]
+    pub offset_mapping: usize,
+}
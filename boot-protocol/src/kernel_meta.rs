@@ -1,7 +1,64 @@
-use x64::mem::addr::VirtAddr;
+use crate::BootInfo;
+use crate::features::FeatureMask;
 
+/// Owner of the ELF note holding [KernelMeta]
+pub const KERNEL_META_NOTE_NAME: &str = "PentOS";
+pub const KERNEL_META_NOTE_TYPE: u32 = 1;
+
+/// Called with the virtual address of [BootInfo] as its only argument, on the hart's own stack
+pub type KernelEntry = extern "C" fn(*const BootInfo) -> !;
+
+/// Declared by the kernel in a [KernelMetaNote], read by the bootloader from the ELF file,
+/// without running any kernel code.
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct KernelMeta {
-    pub bsp_entry: VirtAddr,
-    pub ap_entry: VirtAddr,
+    pub bsp_entry: Option<KernelEntry>,
+    pub ap_entry: Option<KernelEntry>,
+    /// [crate::BOOT_PROTOCOL_VERSION] the kernel was built against
+    pub protocol_version: u32,
+    /// Features the kernel cannot run without
+    pub required_features: FeatureMask,
+}
+
+/// PT_NOTE layout, the descriptor follows the 4 byte padded name without any extra padding
+#[repr(C, packed(4))]
+pub struct KernelMetaNote {
+    name_size: u32,
+    desc_size: u32,
+    ty: u32,
+    name: [u8; 8],
+    desc: KernelMeta,
+}
+
+impl KernelMetaNote {
+    pub const fn new(meta: KernelMeta) -> Self {
+        let mut name = [0; 8];
+        let mut i = 0;
+        while i < KERNEL_META_NOTE_NAME.len() {
+            name[i] = KERNEL_META_NOTE_NAME.as_bytes()[i];
+            i += 1;
+        }
+        Self {
+            // Counting the NUL terminator
+            name_size: KERNEL_META_NOTE_NAME.len() as u32 + 1,
+            desc_size: size_of::<KernelMeta>() as u32,
+            ty: KERNEL_META_NOTE_TYPE,
+            name,
+            desc: meta,
+        }
+    }
+}
+
+impl KernelMeta {
+    /// Reads the descriptor of a [KernelMetaNote]
+    pub fn from_note_desc(desc: &[u8]) -> Option<Self> {
+        if desc.len() != size_of::<Self>() {
+            return None;
+        }
+        Some(unsafe {
+            // SAFETY: Right size, all bit patterns are valid for every field
+            desc.as_ptr().cast::<Self>().read_unaligned()
+        })
+    }
 }
@@ -1,25 +1,196 @@
 #![no_std]
+#![feature(const_trait_impl)]
 
+#[cfg(test)]
+extern crate alloc;
+#[cfg(test)]
+mod test;
+
+pub mod acpi;
+pub mod bootlog;
+pub mod cmdline;
 pub mod features;
+pub mod ffi;
 pub mod framebuffer;
+pub mod kaslr;
 pub mod kernel_meta;
+pub mod mmap;
+pub mod module;
+pub mod serial;
+pub mod timing;
 pub mod topology;
 
+use acpi::AcpiInfo;
+use cmdline::CmdLine;
+use common::collections::smallvec::SmallVec;
+use config::topology::hart::MAX_HART_COUNT;
+use config::vmem::LOCAL_MMIO_REGION;
 use features::FeatureSet;
+use ffi::RawSlice;
 use framebuffer::FramebufferInfo;
-use x64::mem::PhysicalMemoryRegion;
+use framebuffer::MAX_FRAMEBUFFER_COUNT;
+use kaslr::KaslrInfo;
+use mmap::MemoryMap;
+use module::MAX_MODULE_COUNT;
+use module::Module;
+use serial::SerialInfo;
+use timing::BootTimeline;
+use topology::HartStack;
+use topology::HartTls;
+use topology::Topology;
+use x64::mem::addr::Address;
 
-const MMAP_PG_COUNT: usize = 1;
-pub const MAX_MMAP_SIZE: usize = MMAP_PG_COUNT * (4096 / core::mem::size_of::<PhysicalMemoryRegion>());
+/// Bumped on every incompatible change to [BootInfo] or [kernel_meta::KernelMeta]
+pub const BOOT_PROTOCOL_VERSION: u32 = 9;
 
-pub const OFFSET_MAPPING: usize = 0xFFFF800000000000;
+pub const BOOTINFO_MAGIC: u64 = u64::from_le_bytes(*b"PentBoot");
 
-pub const STACK_SIZE: usize = 512 * 1024;
+/// The local APIC of every hart is mapped here, see [config::vmem::LOCAL_MMIO_REGION]
+pub const LOCAL_APIC_MAPPING: usize = LOCAL_MMIO_REGION.start().as_usize();
 
+/// Handed to the kernel entry points. Only use it through [BootInfo::validate],
+/// the kernel and bootloader may come from different builds.
 #[repr(C, align(4096))]
 pub struct BootInfo {
-    pub mmap: [PhysicalMemoryRegion; MAX_MMAP_SIZE],
-    pub mmap_len: usize,
+    pub header: BootInfoHeader,
+    pub mmap: MemoryMap,
     pub features: FeatureSet,
-    pub framebuffer: FramebufferInfo,
+    /// Primary output first, empty when booted headless, see [BootInfo::framebuffer]
+    pub framebuffers: SmallVec<FramebufferInfo, MAX_FRAMEBUFFER_COUNT>,
+    pub stacks: SmallVec<HartStack, MAX_HART_COUNT>,
+    /// Kernel thread-locals of every hart, from the PT_TLS segment of the kernel
+    pub tls: SmallVec<HartTls, MAX_HART_COUNT>,
+    pub topology: Topology,
+    pub acpi: AcpiInfo,
+    pub cmdline: CmdLine,
+    pub modules: SmallVec<Module, MAX_MODULE_COUNT>,
+    pub serial: SerialInfo,
+    /// Bootloader log records, see [bootlog::BootLog::open], empty if there are none
+    pub log: RawSlice<u8>,
+    /// Bootloader phases, the last one ends at kernel entry
+    pub timeline: BootTimeline,
+    pub kaslr: KaslrInfo,
+}
+
+/// Comes first in [BootInfo], and must never change
+#[repr(C)]
+pub struct BootInfoHeader {
+    magic: u64,
+    version: u32,
+    /// Size of the whole [BootInfo]
+    size: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootInfoError {
+    Null,
+    Misaligned,
+    BadMagic(u64),
+    VersionMismatch { found: u32, expected: u32 },
+    SizeMismatch { found: u32, expected: u32 },
+    Corrupt(&'static str),
+}
+
+impl BootInfoHeader {
+    pub const fn new() -> Self {
+        Self {
+            magic: BOOTINFO_MAGIC,
+            version: BOOT_PROTOCOL_VERSION,
+            size: size_of::<BootInfo>() as u32,
+        }
+    }
+}
+
+impl Default for BootInfoHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BootInfo {
+    /// Primary framebuffer, if there is any display
+    #[inline]
+    pub fn framebuffer(&self) -> Option<&FramebufferInfo> {
+        self.framebuffers.first()
+    }
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.framebuffers.is_empty()
+    }
+}
+
+impl BootInfo {
+    /// Checks that `bootinfo` comes from a bootloader speaking the same protocol version,
+    /// before looking at anything past the header.
+    ///
+    /// # Safety
+    /// `bootinfo` must be null, or readable for at least the size of a [BootInfoHeader]
+    /// and for the size it claims.
+    pub unsafe fn validate<'a>(bootinfo: *const BootInfo) -> Result<&'a BootInfo, BootInfoError> {
+        if bootinfo.is_null() {
+            return Err(BootInfoError::Null);
+        }
+        if !bootinfo.is_aligned() {
+            return Err(BootInfoError::Misaligned);
+        }
+        let header = unsafe {
+            // SAFETY: Guaranteed by the caller, the header layout never changes
+            &*bootinfo.cast::<BootInfoHeader>()
+        };
+        if header.magic != BOOTINFO_MAGIC {
+            return Err(BootInfoError::BadMagic(header.magic));
+        }
+        if header.version != BOOT_PROTOCOL_VERSION {
+            return Err(BootInfoError::VersionMismatch {
+                found: header.version,
+                expected: BOOT_PROTOCOL_VERSION,
+            });
+        }
+        if header.size as usize != size_of::<BootInfo>() {
+            return Err(BootInfoError::SizeMismatch {
+                found: header.size,
+                expected: size_of::<BootInfo>() as u32,
+            });
+        }
+
+        let bootinfo = unsafe {
+            // SAFETY: Same version and size, so same layout
+            &*bootinfo
+        };
+        // Nothing past the header can be trusted until checked, enums and bools are only
+        // read as integers by the checks
+        if !bootinfo.mmap.is_valid() {
+            return Err(BootInfoError::Corrupt("memory map"));
+        }
+        if !bootinfo.features.is_valid() {
+            return Err(BootInfoError::Corrupt("features"));
+        }
+        if !bootinfo.framebuffers.is_valid()
+            || !bootinfo.framebuffers.iter().all(FramebufferInfo::is_valid)
+        {
+            return Err(BootInfoError::Corrupt("framebuffers"));
+        }
+        if !bootinfo.stacks.is_valid() {
+            return Err(BootInfoError::Corrupt("stacks"));
+        }
+        if !bootinfo.tls.is_valid() {
+            return Err(BootInfoError::Corrupt("TLS blocks"));
+        }
+        if !bootinfo.topology.is_valid() {
+            return Err(BootInfoError::Corrupt("topology"));
+        }
+        if !bootinfo.cmdline.is_valid() {
+            return Err(BootInfoError::Corrupt("command line"));
+        }
+        if !bootinfo.modules.is_valid() || !bootinfo.modules.iter().all(Module::is_valid) {
+            return Err(BootInfoError::Corrupt("modules"));
+        }
+        if !bootinfo.log.is_valid() {
+            return Err(BootInfoError::Corrupt("boot log"));
+        }
+        if !bootinfo.timeline.is_valid() {
+            return Err(BootInfoError::Corrupt("timeline"));
+        }
+        Ok(bootinfo)
+    }
 }
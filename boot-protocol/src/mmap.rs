@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod test;
+
+use crate::ffi::raw_discriminant;
+use core::ops::Deref;
+use core::slice;
+use x64::mem::PhysicalMemoryRegion;
+
+const MMAP_PG_COUNT: usize = 4;
+pub const MAX_MMAP_SIZE: usize = MMAP_PG_COUNT * 4096 / core::mem::size_of::<MemoryMapEntry>();
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryMapEntry {
+    pub region: PhysicalMemoryRegion,
+    pub kind: MemoryKind,
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryKind {
+    /// Free RAM
+    Usable,
+    /// Bootloader image and data, including the kernel ELF file, the AP trampoline and AP boot
+    /// stacks, and firmware boot services memory, which holds the GDT and IDT the kernel is
+    /// entered with. Free once the kernel is done with bootloader provided pointers outside of
+    /// [crate::BootInfo], and every hart has loaded the kernel's own descriptor tables
+    BootloaderReclaimable,
+    /// Page tables of the mapping the kernel is entered with
+    PageTables,
+    /// Loaded kernel segments
+    KernelImage,
+    /// Kernel stacks, see [crate::topology::HartStack]
+    KernelStack,
+    /// Kernel thread-local storage blocks, see [crate::topology::HartTls]
+    KernelTls,
+    /// [crate::BootInfo] and what it points to
+    BootInfo,
+    /// Contents of boot modules, see [crate::module::Module]
+    Module,
+    /// RAM under 1M, the kernel ignores it apart from AP bootstrapping
+    SuperLowMemory,
+    /// ACPI tables, free once parsed
+    AcpiReclaimable,
+    AcpiNvs,
+    RuntimeServicesCode,
+    RuntimeServicesData,
+    Mmio,
+    Framebuffer,
+    PersistentMemory,
+    /// Faulty RAM
+    Unusable,
+    Reserved,
+}
+
+impl MemoryKind {
+    /// Whether `raw` is the discriminant of a kind
+    pub(crate) const fn is_valid_raw(raw: u32) -> bool {
+        raw <= Self::Reserved as u32
+    }
+}
+
+/// Physical memory map, sorted by start address once [MemoryMap::normalize] has been called
+#[repr(C)]
+pub struct MemoryMap {
+    entries: [MemoryMapEntry; MAX_MMAP_SIZE],
+    len: usize,
+}
+
+impl MemoryMapEntry {
+    #[inline]
+    pub const fn new(region: PhysicalMemoryRegion, kind: MemoryKind) -> Self {
+        Self { region, kind }
+    }
+}
+
+impl MemoryMap {
+    pub const fn new() -> Self {
+        Self {
+            entries: [MemoryMapEntry::new(PhysicalMemoryRegion::null(), MemoryKind::Reserved);
+                MAX_MMAP_SIZE],
+            len: 0,
+        }
+    }
+}
+
+impl MemoryMap {
+    /// Adds `entry`, which must not overlap any other entry.
+    /// Gives `entry` back if the map is full, even after normalization.
+    pub fn push(&mut self, entry: MemoryMapEntry) -> Result<(), MemoryMapEntry> {
+        if entry.region.is_null() {
+            return Ok(());
+        }
+        if let Some(last) = self.entries[..self.len].last_mut() {
+            if Self::mergeable(last, &entry) {
+                last.region += entry.region;
+                return Ok(());
+            }
+        }
+        if self.len == MAX_MMAP_SIZE {
+            self.normalize();
+            if self.len == MAX_MMAP_SIZE {
+                return Err(entry);
+            }
+        }
+        self.entries[self.len] = entry;
+        self.len += 1;
+        Ok(())
+    }
+    /// Adds `entry`, cutting it out of the entries it overlaps
+    pub fn overlay(&mut self, entry: MemoryMapEntry) -> Result<(), MemoryMapEntry> {
+        while let Some(i) = self
+            .iter()
+            .position(|other| !other.region.intersect(entry.region).is_null())
+        {
+            let other = self.entries[i];
+            let overlap = other.region.intersect(entry.region);
+            self.entries.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+            let before =
+                PhysicalMemoryRegion::new_boundaries(other.region.start(), overlap.start());
+            let after = PhysicalMemoryRegion::new_boundaries(overlap.end(), other.region.end());
+            self.push(MemoryMapEntry::new(before, other.kind))?;
+            self.push(MemoryMapEntry::new(after, other.kind))?;
+        }
+        self.push(entry)
+    }
+    /// Sorts entries by start address and merges contiguous entries of the same kind
+    pub fn normalize(&mut self) {
+        self.entries[..self.len].sort_unstable_by_key(|entry| entry.region.start());
+        let mut len = 0;
+        for i in 0..self.len {
+            let entry = self.entries[i];
+            if len > 0 && Self::mergeable(&self.entries[len - 1], &entry) {
+                self.entries[len - 1].region += entry.region;
+            } else {
+                self.entries[len] = entry;
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+    pub(crate) fn is_valid(&self) -> bool {
+        self.len <= MAX_MMAP_SIZE
+            && (0..self.len).all(|i| {
+                let kind = unsafe {
+                    // SAFETY: In the array, read as an integer since it may be anything
+                    raw_discriminant(&raw const (*self.entries.as_ptr().add(i)).kind)
+                };
+                MemoryKind::is_valid_raw(kind)
+            })
+    }
+    #[inline]
+    fn mergeable(first: &MemoryMapEntry, second: &MemoryMapEntry) -> bool {
+        first.kind == second.kind && first.region.end() == second.region.start()
+    }
+}
+
+impl MemoryMap {
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<MemoryMapEntry> {
+        self.entries[..self.len].iter()
+    }
+    /// Regions of a given kind
+    pub fn regions(&self, kind: MemoryKind) -> impl Iterator<Item = PhysicalMemoryRegion> + '_ {
+        self.iter()
+            .filter(move |entry| entry.kind == kind)
+            .map(|entry| entry.region)
+    }
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MemoryMap {
+    type Target = [MemoryMapEntry];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MemoryMap {
+    type Item = &'a MemoryMapEntry;
+    type IntoIter = slice::Iter<'a, MemoryMapEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
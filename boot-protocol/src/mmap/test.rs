@@ -0,0 +1,89 @@
+use super::MAX_MMAP_SIZE;
+use super::MemoryKind;
+use super::MemoryMap;
+use super::MemoryMapEntry;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+
+fn entry(start: usize, size: usize, kind: MemoryKind) -> MemoryMapEntry {
+    MemoryMapEntry::new(
+        PhysicalMemoryRegion::new(PhysAddr::new_truncate(start), MemorySize::new(size)),
+        kind,
+    )
+}
+
+#[test]
+fn test_push_merges_contiguous() {
+    let mut mmap = MemoryMap::new();
+    mmap.push(entry(0x1000, 0x1000, MemoryKind::Usable))
+        .unwrap();
+    mmap.push(entry(0x2000, 0x1000, MemoryKind::Usable))
+        .unwrap();
+    mmap.push(entry(0x3000, 0x1000, MemoryKind::PageTables))
+        .unwrap();
+    mmap.push(entry(0x5000, 0x1000, MemoryKind::PageTables))
+        .unwrap();
+    mmap.push(entry(0x9000, 0, MemoryKind::Reserved)).unwrap();
+    assert_eq!(
+        &*mmap,
+        &[
+            entry(0x1000, 0x2000, MemoryKind::Usable),
+            entry(0x3000, 0x1000, MemoryKind::PageTables),
+            entry(0x5000, 0x1000, MemoryKind::PageTables),
+        ]
+    );
+}
+
+#[test]
+fn test_normalize() {
+    let mut mmap = MemoryMap::new();
+    mmap.push(entry(0x3000, 0x1000, MemoryKind::Usable))
+        .unwrap();
+    mmap.push(entry(0x1000, 0x1000, MemoryKind::Usable))
+        .unwrap();
+    mmap.push(entry(0x4000, 0x1000, MemoryKind::AcpiNvs))
+        .unwrap();
+    mmap.push(entry(0x2000, 0x1000, MemoryKind::Usable))
+        .unwrap();
+    mmap.normalize();
+    assert_eq!(
+        &*mmap,
+        &[
+            entry(0x1000, 0x3000, MemoryKind::Usable),
+            entry(0x4000, 0x1000, MemoryKind::AcpiNvs),
+        ]
+    );
+}
+
+#[test]
+fn test_overlay() {
+    let mut mmap = MemoryMap::new();
+    mmap.push(entry(0x0000, 0x4000, MemoryKind::Reserved))
+        .unwrap();
+    mmap.push(entry(0x4000, 0x4000, MemoryKind::Mmio)).unwrap();
+    mmap.overlay(entry(0x1000, 0x4000, MemoryKind::Framebuffer))
+        .unwrap();
+    mmap.normalize();
+    assert_eq!(
+        &*mmap,
+        &[
+            entry(0x0000, 0x1000, MemoryKind::Reserved),
+            entry(0x1000, 0x4000, MemoryKind::Framebuffer),
+            entry(0x5000, 0x3000, MemoryKind::Mmio),
+        ]
+    );
+}
+
+#[test]
+fn test_overflow() {
+    let mut mmap = MemoryMap::new();
+    for i in 0..MAX_MMAP_SIZE {
+        mmap.push(entry(i * 0x2000, 0x1000, MemoryKind::Usable))
+            .unwrap();
+    }
+    let last = entry(MAX_MMAP_SIZE * 0x2000, 0x1000, MemoryKind::Usable);
+    assert_eq!(mmap.push(last), Err(last));
+    assert_eq!(mmap.len(), MAX_MMAP_SIZE);
+}
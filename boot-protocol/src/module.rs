@@ -0,0 +1,57 @@
+use core::slice;
+use core::str;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+
+pub const MAX_MODULE_COUNT: usize = 16;
+pub const MAX_MODULE_NAME_LEN: usize = 64;
+
+/// File loaded by the bootloader along with the kernel, mapped read-only at `virt`
+#[repr(C)]
+pub struct Module {
+    name: [u8; MAX_MODULE_NAME_LEN],
+    name_len: usize,
+    pub phys: PhysicalMemoryRegion,
+    pub virt: VirtualMemoryRegion,
+}
+
+impl Module {
+    /// Returns `None` if `name` is longer than [MAX_MODULE_NAME_LEN] bytes
+    pub fn new(name: &str, phys: PhysicalMemoryRegion, virt: VirtualMemoryRegion) -> Option<Self> {
+        let mut bytes = [0; MAX_MODULE_NAME_LEN];
+        bytes
+            .get_mut(..name.len())?
+            .copy_from_slice(name.as_bytes());
+        Some(Self {
+            name: bytes,
+            name_len: name.len(),
+            phys,
+            virt,
+        })
+    }
+}
+
+impl Module {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.name
+            .get(..self.name_len)
+            .is_some_and(|name| str::from_utf8(name).is_ok())
+    }
+    pub fn name(&self) -> &str {
+        unsafe {
+            // SAFETY: Copied from a str in `new`
+            str::from_utf8_unchecked(&self.name[..self.name_len])
+        }
+    }
+    /// Contents of the module, through its mapping
+    ///
+    /// # Safety
+    /// The mapping set up by the bootloader must still be in place
+    pub unsafe fn data(&self) -> &'static [u8] {
+        unsafe {
+            // SAFETY: Guaranteed by the caller
+            slice::from_raw_parts(self.virt.start().as_ptr(), *self.virt.size())
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/// 16550 UART the bootloader logs to, from the ACPI SPCR table, or COM1 by default
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SerialInfo {
+    /// 0 if there is no serial port
+    port: u16,
+    pub baud: u32,
+}
+
+impl SerialInfo {
+    pub const fn new(port: u16, baud: u32) -> Self {
+        Self { port, baud }
+    }
+    pub const fn none() -> Self {
+        Self { port: 0, baud: 0 }
+    }
+}
+
+impl SerialInfo {
+    /// First IO port of the UART, already set up at `baud` by the bootloader
+    pub fn port(&self) -> Option<u16> {
+        (self.port != 0).then_some(self.port)
+    }
+}
@@ -0,0 +1,291 @@
+use crate::BootInfo;
+use crate::BootInfoError;
+use crate::BootInfoHeader;
+use crate::acpi::AcpiInfo;
+use crate::cmdline::CmdLine;
+use crate::features::FeatureSet;
+use crate::features::Vendor;
+use crate::ffi::RawSlice;
+use crate::framebuffer::FramebufferInfo;
+use crate::kaslr::KaslrInfo;
+use crate::mmap::MemoryKind;
+use crate::mmap::MemoryMap;
+use crate::mmap::MemoryMapEntry;
+use crate::module::Module;
+use crate::serial::SerialInfo;
+use crate::timing::BootPhase;
+use crate::timing::BootTimeline;
+use crate::topology::Hart;
+use crate::topology::HartStack;
+use crate::topology::Topology;
+use alloc::boxed::Box;
+use alloc::vec;
+use common::collections::smallvec::SmallVec;
+use core::mem;
+use x64::framebuffer::PixelBitmask;
+use x64::framebuffer::PixelMode;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+
+/// Leaked, as corrupted vectors can not be dropped
+fn bootinfo() -> &'static mut BootInfo {
+    let mut bootinfo = Box::new(BootInfo {
+        header: BootInfoHeader::new(),
+        mmap: MemoryMap::new(),
+        features: FeatureSet {
+            vendor: Vendor::AuthenticAMD,
+            context_id: true,
+            inv_context_id: false,
+            shadow_stack: false,
+            pk_user: false,
+            pk_super: false,
+        },
+        framebuffers: SmallVec::new(),
+        stacks: SmallVec::new(),
+        tls: SmallVec::new(),
+        topology: Topology::new(),
+        acpi: AcpiInfo {
+            rsdp: PhysAddr::null(),
+            xsdt: PhysAddr::null(),
+        },
+        cmdline: CmdLine::new("quiet").unwrap(),
+        modules: SmallVec::new(),
+        serial: SerialInfo::none(),
+        log: RawSlice::new(vec![0; 64].leak()),
+        timeline: BootTimeline::new(0),
+        kaslr: KaslrInfo {
+            kernel_slide: 0,
+            offset_mapping: 0,
+        },
+    });
+    let region = PhysicalMemoryRegion::new(PhysAddr::new_panic(0x10_0000), MemorySize::new(0x1000));
+    bootinfo
+        .mmap
+        .push(MemoryMapEntry::new(region, MemoryKind::Usable))
+        .unwrap();
+    let _ = bootinfo.framebuffers.push(FramebufferInfo {
+        phys: PhysAddr::null(),
+        fb: RawSlice::new(vec![0; 4 * 8 * 2].leak()),
+        width: 6,
+        height: 2,
+        stride: 8,
+        mode: PixelMode::BgrRs,
+        bytes_per_pixel: 4,
+        buffer: RawSlice::new(vec![0; 4 * 8 * 2].leak()),
+    });
+    let _ = bootinfo.stacks.push(HartStack {
+        apic_id: 0,
+        region: VirtualMemoryRegion::null(),
+    });
+    let _ = bootinfo.topology.harts.push(Hart {
+        apic_id: 0,
+        acpi_id: 0,
+        bsp: true,
+    });
+    let module = Module::new(
+        "initrd",
+        PhysicalMemoryRegion::null(),
+        VirtualMemoryRegion::null(),
+    );
+    let _ = bootinfo.modules.push(module.unwrap());
+    bootinfo.timeline.record(BootPhase::CedeControl, 1, 1);
+    Box::leak(bootinfo)
+}
+
+fn validate(bootinfo: &BootInfo) -> Result<(), BootInfoError> {
+    unsafe {
+        // SAFETY: A whole BootInfo
+        BootInfo::validate(bootinfo)
+    }
+    .map(|_| ())
+}
+
+/// Writes `raw` over `field` of `bootinfo`
+fn corrupt<T, F>(bootinfo: &mut BootInfo, field: impl FnOnce(&BootInfo) -> *const F, raw: T) {
+    let field = field(bootinfo);
+    let offset = field as usize - (&raw const *bootinfo) as usize;
+    assert!(offset + mem::size_of::<T>() <= mem::size_of::<BootInfo>());
+    unsafe {
+        // SAFETY: In `bootinfo`, as checked
+        (&raw mut *bootinfo)
+            .cast::<u8>()
+            .add(offset)
+            .cast::<T>()
+            .write_unaligned(raw);
+    }
+}
+
+/// Makes the length of `vec` one more than its capacity
+fn corrupt_len<T, const N: usize>(
+    bootinfo: &mut BootInfo,
+    vec: impl FnOnce(&BootInfo) -> *const SmallVec<T, N>,
+) {
+    // The length comes right after the buffer, and all elements are 8 byte aligned
+    let len = vec(bootinfo)
+        .cast::<u8>()
+        .wrapping_add(mem::size_of::<SmallVec<T, N>>() - 8);
+    corrupt(bootinfo, |_| len, N + 1);
+}
+
+#[test]
+fn test_valid() {
+    assert_eq!(validate(bootinfo()), Ok(()));
+}
+
+#[test]
+fn test_lengths() {
+    let bootinfo = bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.framebuffers);
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.stacks);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("stacks")));
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.tls);
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("TLS blocks"))
+    );
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.topology.harts);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("topology")));
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| {
+        &raw const bootinfo.topology.int_controllers
+    });
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("topology")));
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.modules);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("modules")));
+
+    let bootinfo = self::bootinfo();
+    corrupt_len(bootinfo, |bootinfo| &raw const bootinfo.timeline.phases);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("timeline")));
+}
+
+#[test]
+fn test_enums() {
+    let bootinfo = bootinfo();
+    let kind = &raw const bootinfo.mmap.iter().next().unwrap().kind;
+    corrupt(bootinfo, |_| kind, MemoryKind::Reserved as u32 + 1);
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("memory map"))
+    );
+
+    let bootinfo = self::bootinfo();
+    corrupt(
+        bootinfo,
+        |bootinfo| &raw const bootinfo.features.vendor,
+        2u32,
+    );
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("features")));
+
+    let bootinfo = self::bootinfo();
+    corrupt(
+        bootinfo,
+        |bootinfo| &raw const bootinfo.features.pk_super,
+        2u8,
+    );
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("features")));
+
+    let bootinfo = self::bootinfo();
+    corrupt(
+        bootinfo,
+        |bootinfo| &raw const bootinfo.framebuffers[0].mode,
+        3u32,
+    );
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    corrupt(
+        bootinfo,
+        |bootinfo| &raw const bootinfo.topology.harts[0].bsp,
+        0xFFu8,
+    );
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("topology")));
+
+    let bootinfo = self::bootinfo();
+    let phase = &raw const bootinfo.timeline.phases[0].phase;
+    corrupt(bootinfo, |_| phase, BootPhase::CedeControl as u32 + 1);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("timeline")));
+}
+
+#[test]
+fn test_slices() {
+    // RawSlice is a pointer then a length
+    let bootinfo = bootinfo();
+    corrupt(bootinfo, |bootinfo| &raw const bootinfo.log, 0usize);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("boot log")));
+
+    let bootinfo = self::bootinfo();
+    corrupt(bootinfo, |bootinfo| &raw const bootinfo.log, usize::MAX - 8);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("boot log")));
+
+    let bootinfo = self::bootinfo();
+    bootinfo.framebuffers[0].height = 3;
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    bootinfo.framebuffers[0].bytes_per_pixel = 0;
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    bootinfo.framebuffers[0].bytes_per_pixel = 3;
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    bootinfo.framebuffers[0].mode = PixelMode::Bitmask(PixelBitmask {
+        red: 0,
+        green: 0,
+        blue: 0,
+        reserved: 0,
+    });
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    bootinfo.framebuffers[0].width = 9;
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    let bootinfo = self::bootinfo();
+    let buffer = (&raw const bootinfo.framebuffers[0].buffer).cast::<usize>();
+    corrupt(bootinfo, |_| buffer.wrapping_add(1), 8usize);
+    assert_eq!(
+        validate(bootinfo),
+        Err(BootInfoError::Corrupt("framebuffers"))
+    );
+
+    // Module names come first
+    let bootinfo = self::bootinfo();
+    corrupt(bootinfo, |bootinfo| &raw const bootinfo.modules[0], 0xFFu8);
+    assert_eq!(validate(bootinfo), Err(BootInfoError::Corrupt("modules")));
+}
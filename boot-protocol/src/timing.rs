@@ -0,0 +1,114 @@
+//! When each phase of the bootloader ran, in time stamp counter ticks.
+//!
+//! Stamps are relative to [BootTimeline::start_tsc], the same origin as
+//! [crate::bootlog] record timestamps.
+
+#[cfg(test)]
+mod test;
+
+use crate::ffi::raw_discriminant;
+use common::collections::smallvec::SmallVec;
+
+pub const MAX_BOOT_PHASES: usize = 16;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootPhase {
+    FeatureDetection,
+    ConfigLoad,
+    AcpiParsing,
+    KernelLoad,
+    ModuleLoad,
+    FramebufferInit,
+    ExitBootServices,
+    PageTables,
+    ApStartup,
+    MemoryMap,
+    /// Only has a start, it ends when the kernel is entered
+    CedeControl,
+}
+
+impl BootPhase {
+    pub const fn name(self) -> &'static str {
+        match self {
+            BootPhase::FeatureDetection => "feature detection",
+            BootPhase::ConfigLoad => "config load",
+            BootPhase::AcpiParsing => "ACPI parsing",
+            BootPhase::KernelLoad => "kernel load",
+            BootPhase::ModuleLoad => "module load",
+            BootPhase::FramebufferInit => "framebuffer init",
+            BootPhase::ExitBootServices => "exit boot services",
+            BootPhase::PageTables => "page tables",
+            BootPhase::ApStartup => "AP startup",
+            BootPhase::MemoryMap => "memory map",
+            BootPhase::CedeControl => "cede control",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTiming {
+    pub phase: BootPhase,
+    pub start: u64,
+    /// Same as `start` for phases that end in the kernel
+    pub end: u64,
+}
+
+impl PhaseTiming {
+    #[inline]
+    pub fn ticks(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone)]
+pub struct BootTimeline {
+    /// Absolute TSC value all stamps are relative to
+    pub start_tsc: u64,
+    /// Calibrated by the bootloader, 0 if unknown
+    pub tsc_frequency: u64,
+    pub phases: SmallVec<PhaseTiming, MAX_BOOT_PHASES>,
+}
+
+impl BootTimeline {
+    pub const fn new(start_tsc: u64) -> Self {
+        Self {
+            start_tsc,
+            tsc_frequency: 0,
+            phases: SmallVec::new(),
+        }
+    }
+}
+
+impl BootTimeline {
+    /// Whether every recorded phase is a [BootPhase]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.phases.is_valid()
+            && (0..self.phases.len()).all(|i| {
+                let phase = unsafe {
+                    // SAFETY: In the vector, read as an integer since it may be anything
+                    raw_discriminant(&raw const (*self.phases.as_ptr().add(i)).phase)
+                };
+                phase <= BootPhase::CedeControl as u32
+            })
+    }
+    /// Phases past [MAX_BOOT_PHASES] are dropped
+    pub fn record(&mut self, phase: BootPhase, start: u64, end: u64) {
+        let _ = self.phases.push(PhaseTiming { phase, start, end });
+    }
+    /// Ticks since [BootTimeline::start_tsc] at absolute TSC value `tsc`
+    #[inline]
+    pub fn since_start(&self, tsc: u64) -> u64 {
+        tsc.saturating_sub(self.start_tsc)
+    }
+    /// `None` if the TSC frequency is unknown
+    pub fn ticks_to_us(&self, ticks: u64) -> Option<u64> {
+        if self.tsc_frequency == 0 {
+            return None;
+        }
+        let us = ticks as u128 * 1_000_000 / self.tsc_frequency as u128;
+        Some(us.min(u64::MAX as u128) as u64)
+    }
+}
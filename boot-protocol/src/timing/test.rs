@@ -0,0 +1,36 @@
+use super::BootPhase;
+use super::BootTimeline;
+use super::MAX_BOOT_PHASES;
+
+#[test]
+fn test_ticks_to_us() {
+    let mut timeline = BootTimeline::new(1000);
+    assert_eq!(timeline.ticks_to_us(3_000_000), None);
+    timeline.tsc_frequency = 3_000_000_000;
+    assert_eq!(timeline.ticks_to_us(0), Some(0));
+    assert_eq!(timeline.ticks_to_us(3_000_000), Some(1000));
+    assert_eq!(timeline.ticks_to_us(2999), Some(0));
+    assert_eq!(timeline.ticks_to_us(u64::MAX), Some(6_148_914_691_236_517));
+}
+
+#[test]
+fn test_since_start() {
+    let timeline = BootTimeline::new(1000);
+    assert_eq!(timeline.since_start(1500), 500);
+    assert_eq!(timeline.since_start(10), 0);
+}
+
+#[test]
+fn test_record() {
+    let mut timeline = BootTimeline::new(0);
+    timeline.record(BootPhase::AcpiParsing, 10, 25);
+    timeline.record(BootPhase::CedeControl, 40, 40);
+    assert_eq!(timeline.phases.len(), 2);
+    assert_eq!(timeline.phases[0].phase, BootPhase::AcpiParsing);
+    assert_eq!(timeline.phases[0].ticks(), 15);
+    assert_eq!(timeline.phases[1].ticks(), 0);
+    for _ in 0..MAX_BOOT_PHASES {
+        timeline.record(BootPhase::PageTables, 50, 60);
+    }
+    assert_eq!(timeline.phases.len(), MAX_BOOT_PHASES);
+}
@@ -1,9 +1,13 @@
+use crate::ffi::is_valid_bool;
 use common::collections::smallvec::SmallVec;
 use config::topology::hart::MAX_HART_COUNT;
 use config::topology::hart::MAX_INTCTL_COUNT;
+use x64::mem::VirtualMemoryRegion;
 use x64::mem::addr::PhysAddr;
+use x64::mem::addr::VirtAddr;
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct Topology {
     pub harts: SmallVec<Hart, MAX_HART_COUNT>,
     pub int_controllers: SmallVec<InterruptController, MAX_INTCTL_COUNT>,
@@ -11,19 +15,39 @@ pub struct Topology {
 
 // Too proud of myself to call this CPU
 #[repr(C)]
+#[derive(Clone)]
 pub struct Hart {
     pub apic_id: usize,
     pub acpi_id: usize,
+    /// Whether this is the hart that ran the bootloader
+    pub bsp: bool,
 }
 
 // Too proud of myself to call this IO APIC
 #[repr(C)]
+#[derive(Clone)]
 pub struct InterruptController {
     pub id: usize,
     pub register_base: PhysAddr,
     pub gsi_base: usize,
 }
 
+/// Main kernel stack of a hart, with an unmapped guard below `region`
+#[repr(C)]
+pub struct HartStack {
+    pub apic_id: usize,
+    pub region: VirtualMemoryRegion,
+}
+
+/// Kernel thread-local storage block of a hart, laid out following the x86_64 variant II.
+/// The hart enters the kernel with its FS base set to `thread_pointer`.
+#[repr(C)]
+pub struct HartTls {
+    pub apic_id: usize,
+    pub region: VirtualMemoryRegion,
+    pub thread_pointer: VirtAddr,
+}
+
 impl Topology {
     pub const fn new() -> Self {
         Self {
@@ -33,8 +57,29 @@ impl Topology {
     }
 }
 
+impl Topology {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.harts.is_valid()
+            && self.int_controllers.is_valid()
+            && (0..self.harts.len()).all(|i| unsafe {
+                // SAFETY: In the vector
+                is_valid_bool(&raw const (*self.harts.as_ptr().add(i)).bsp)
+            })
+    }
+    pub fn bsp(&self) -> Option<&Hart> {
+        self.harts.iter().find(|hart| hart.bsp)
+    }
+}
+
 impl Default for Topology {
     fn default() -> Self {
         Self::new()
     }
 }
+
+impl HartStack {
+    /// Initial stack pointer
+    pub fn top(&self) -> VirtAddr {
+        self.region.end()
+    }
+}
@@ -1,14 +1,22 @@
 mod madt;
+mod spcr;
 mod xsdt;
 
 use acpi::table::Madt;
 use acpi::table::MadtEntryHeader;
 use acpi::table::Rsdp;
+use acpi::table::Spcr;
+use acpi::table::Xsdt;
+use boot_protocol::acpi::AcpiInfo;
+use boot_protocol::serial::SerialInfo;
+use log::warn;
 use spinlocks::once::Once;
 use uefi::system;
 use uefi::table;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
 
-pub fn init() {
+pub fn init() -> AcpiInfo {
     let rsdp: Once<Option<&Rsdp>> = Once::new();
     system::with_config_table(|table| {
         rsdp.init(|| {
@@ -35,6 +43,24 @@ pub fn init() {
     }
     let xsdt = rsdp.xsdt();
     xsdt::parse(xsdt);
+
+    AcpiInfo {
+        rsdp: PhysAddr::new_panic(rsdp as *const _ as usize),
+        xsdt: PhysAddr::new_panic(xsdt as *const _ as usize),
+    }
+}
+
+/// Serial port to log to once the UEFI console is gone, see [spcr::parse]
+pub fn serial_port(acpi: &AcpiInfo) -> SerialInfo {
+    let xsdt = unsafe {
+        // SAFETY: Verified in init, and still identity mapped by UEFI
+        acpi.xsdt.to_ref::<Xsdt>()
+    };
+    let spcr = xsdt.find::<Spcr>().unwrap_or_else(|_| {
+        warn!("Corrupt SPCR table, ignoring it");
+        None
+    });
+    spcr::parse(spcr)
 }
 
 fn is_lapic_or_ioapic(entry: &MadtEntryHeader) -> bool {
@@ -3,6 +3,7 @@ use acpi::table::LocalApic;
 use acpi::table::Madt;
 use boot_protocol::topology::Hart;
 use boot_protocol::topology::InterruptController;
+use x64::lapic;
 use x64::mem::addr::Address;
 use x64::mem::addr::PhysAddr;
 
@@ -26,6 +27,7 @@ fn parse_lapic(lapic: &LocalApic) {
     register_hart(Hart {
         apic_id: lapic.apic_id as usize,
         acpi_id: lapic.proc_uid as usize,
+        bsp: lapic.apic_id as usize == lapic::id_cpuid(),
     });
 }
 
@@ -0,0 +1,29 @@
+use acpi::table::GenericAddress;
+use acpi::table::Spcr;
+use boot_protocol::serial::SerialInfo;
+use log::info;
+use log::warn;
+use x64::serial::COM1;
+
+/// When the firmware doesn't say
+const DEFAULT_BAUD: u32 = 115_200;
+
+pub fn parse(spcr: Option<&Spcr>) -> SerialInfo {
+    let Some(spcr) = spcr else {
+        info!("No SPCR table, assuming COM1 for serial logging");
+        return SerialInfo::new(COM1, DEFAULT_BAUD);
+    };
+    let address_space = spcr.base_address.address_space;
+    let address = spcr.base_address.address;
+    if !spcr.is_16550() || address_space != GenericAddress::SYSTEM_IO || address == 0 {
+        warn!("SPCR console is not a 16550 UART on IO ports, no serial logging");
+        return SerialInfo::none();
+    }
+    let Ok(port) = u16::try_from(address) else {
+        warn!("SPCR console at invalid IO port {address:#x}, no serial logging");
+        return SerialInfo::none();
+    };
+    let baud = spcr.baud_rate().unwrap_or(DEFAULT_BAUD);
+    info!("SPCR console at IO port {port:#x}, {baud} baud");
+    SerialInfo::new(port, baud)
+}
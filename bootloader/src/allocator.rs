@@ -1,6 +1,9 @@
+mod lowmem;
 mod postboot;
 mod preboot;
 
+pub use lowmem::LOWMEM_ALLOCATOR;
+pub use lowmem::reserve_superlowmem;
 pub use postboot::ALLOCATOR_CAP;
 pub use postboot::PostBootAllocator;
 pub use preboot::PreBootAllocator;
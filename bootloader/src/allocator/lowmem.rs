@@ -0,0 +1,45 @@
+use crate::bootstage;
+use config::pmem::SUPERLOWMEM;
+use mman::phys::lowmem::LowMemAllocator;
+use uefi::boot;
+use uefi::boot::AllocateType;
+use uefi::boot::MemoryType;
+use uefi::mem::memory_map::MemoryMap;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+use x64::mem::frame::Frame;
+use x64::mem::frame::size::Frame64KiB;
+use x64::mem::frame::size::FrameSize;
+
+/// Allocator for memory below 1M, only ever contains what [reserve_superlowmem] took from the firmware
+pub static LOWMEM_ALLOCATOR: LowMemAllocator = LowMemAllocator::new();
+
+/// Takes every free 64K chunk of super low memory from the firmware, and gives it to [LOWMEM_ALLOCATOR]
+pub fn reserve_superlowmem() {
+    if !bootstage::is_preboot() {
+        panic!("Super low memory can only be reserved before exiting boot services");
+    }
+    let mmap = boot::memory_map(MemoryType::LOADER_DATA).expect("Failed to get memory map");
+    for entry in mmap.entries() {
+        if entry.ty != MemoryType::CONVENTIONAL {
+            continue;
+        }
+        let start = (entry.phys_start as usize).next_multiple_of(Frame64KiB::SIZE);
+        let end = (entry.phys_start as usize + entry.page_count as usize * 4096)
+            .min(SUPERLOWMEM.end().as_usize());
+
+        // Chunk 0 holds the real mode IVT and BDA, leave it alone
+        let mut chunk = start.max(Frame64KiB::SIZE);
+        while chunk + Frame64KiB::SIZE <= end {
+            let reserved = boot::allocate_pages(
+                AllocateType::Address(chunk as u64),
+                MemoryType::LOADER_DATA,
+                Frame64KiB::SIZE / 4096,
+            );
+            if reserved.is_ok() {
+                LOWMEM_ALLOCATOR.free64k(Frame::containing(PhysAddr::new_panic(chunk)));
+            }
+            chunk += Frame64KiB::SIZE;
+        }
+    }
+}
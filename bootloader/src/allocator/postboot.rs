@@ -1,12 +1,28 @@
 use crate::phys_mmap::PhysMemMap;
+use boot_protocol::mmap::MemoryKind;
+use boot_protocol::mmap::MemoryMap;
+use boot_protocol::mmap::MemoryMapEntry;
+use common::collections::freelist::FreeList;
+use config::pmem::HIGHMEM;
+use config::pmem::LOWMEM;
+use config::pmem::MIDMEM;
 use core::mem;
-use x64::mem::addr::{Address, PhysAddr};
+use core::ops::Range;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
 
 pub const ALLOCATOR_CAP: usize = 256;
 
-/// Post boot services allocator
+/// Zones tried in order by [PostBootAllocator::alloc_raw], low memory is kept for devices that need it
+const ZONE_PREFERENCE: [PhysicalMemoryRegion; 3] = [HIGHMEM, MIDMEM, LOWMEM];
+
+/// Post boot services allocator, first fit over a [FreeList] of physical addresses
 pub struct PostBootAllocator<const MAX: usize> {
-    mmap: PhysMemMap<MAX>,
+    free: FreeList<MAX>,
+    /// Everything handed out so far, by kind
+    used: MemoryMap,
 }
 
 impl<const MAX: usize> PostBootAllocator<MAX> {
@@ -15,45 +31,77 @@ impl<const MAX: usize> PostBootAllocator<MAX> {
     /// as well as LOADER_CODE and LOADER_DATA regions.
     pub unsafe fn init(mut mmap: PhysMemMap<MAX>) -> Self {
         mmap.minimize();
-        mmap.sort_size();
-        Self { mmap }
+        Self {
+            free: FreeList::new(mmap.iter().map(range)),
+            used: MemoryMap::new(),
+        }
     }
 
-    pub fn fini<const LOADER_MAX: usize>(
-        self,
-        loader_mmap: PhysMemMap<LOADER_MAX>,
-    ) -> PhysMemMap<MAX> {
-        let Self { mut mmap } = self;
-        for region in loader_mmap.iter() {
-            mmap.add(*region);
+    /// Returns the memory left, and the memory allocated
+    pub fn fini(self) -> (PhysMemMap<MAX>, MemoryMap) {
+        let Self { free, mut used } = self;
+        let mut mmap = PhysMemMap::new();
+        for range in free.iter() {
+            mmap.regions[mmap.len] = PhysicalMemoryRegion::new_boundaries(
+                PhysAddr::new_panic(range.start),
+                PhysAddr::new_panic(range.end),
+            );
+            mmap.len += 1;
         }
         mmap.minimize();
-        mmap.sort_start_addr();
-        mmap
+        used.normalize();
+        (mmap, used)
     }
 }
 
 impl<const MAX: usize> PostBootAllocator<MAX> {
-    pub fn alloc_raw(&mut self, size: usize, align: usize) -> Option<PhysAddr> {
-        if align > 4096 || !align.is_power_of_two() {
+    /// Allocates from high memory first, then middle memory, then low memory.
+    /// `align` is rounded up to 4096, `size` to a multiple of 4096.
+    pub fn alloc_raw(&mut self, size: usize, align: usize, kind: MemoryKind) -> Option<PhysAddr> {
+        self.alloc_raw_from(&ZONE_PREFERENCE, size, align, kind)
+    }
+    /// Same as [PostBootAllocator::alloc_raw], only from memory inside `zone`, e.g. [LOWMEM]
+    pub fn alloc_raw_in(
+        &mut self,
+        zone: PhysicalMemoryRegion,
+        size: usize,
+        align: usize,
+        kind: MemoryKind,
+    ) -> Option<PhysAddr> {
+        self.alloc_raw_from(&[zone], size, align, kind)
+    }
+    /// Allocates from the first of `zones` with room
+    fn alloc_raw_from(
+        &mut self,
+        zones: &[PhysicalMemoryRegion],
+        size: usize,
+        align: usize,
+        kind: MemoryKind,
+    ) -> Option<PhysAddr> {
+        if !align.is_power_of_two() {
             return None;
         }
-        let size = size.next_multiple_of(4096);
-        for region in self.mmap.iter_mut() {
-            if *region.size() >= size {
-                let start = region.take_start(size);
-                // Ya we sort twice on each allocations, tu as un problème?
-                self.mmap.minimize();
-                self.mmap.sort_size();
-                return Some(start);
-            }
+        let align = align.max(4096);
+        let size = size.max(1).next_multiple_of(4096);
+        let start = self.free.alloc(zones.iter().map(range), size, align)?;
+        let start = PhysAddr::new_panic(start);
+        let allocated = PhysicalMemoryRegion::new(start, MemorySize::new(size));
+        if self
+            .used
+            .push(MemoryMapEntry::new(allocated, kind))
+            .is_err()
+        {
+            panic!("Too many allocations to keep track of");
         }
-        None
+        Some(start)
     }
-    pub fn alloc<'a, T>(&mut self, init: T) -> Option<&'a mut T> {
+}
+
+impl<const MAX: usize> PostBootAllocator<MAX> {
+    pub fn alloc<'a, T>(&mut self, init: T, kind: MemoryKind) -> Option<&'a mut T> {
         let size = mem::size_of::<T>();
         let align = mem::align_of::<T>();
-        let start = self.alloc_raw(size, align)?;
+        let start = self.alloc_raw(size, align, kind)?;
         let ptr = start.as_mut_ptr::<T>();
         unsafe {
             // SAFETY: `start` is (over)aligned, and cannot be null since we dismiss all memory below 1M
@@ -62,3 +110,7 @@ impl<const MAX: usize> PostBootAllocator<MAX> {
         }
     }
 }
+
+fn range(region: &PhysicalMemoryRegion) -> Range<usize> {
+    region.start().as_usize()..region.end().as_usize()
+}
@@ -0,0 +1,149 @@
+//! Boot configuration, read from `pentos.cfg` next to the bootloader image.
+//! Every key is optional, unknown keys are ignored.
+//!
+//! ```text
+//! # Path from the root of the boot volume
+//! kernel = \pentos.kernel
+//! log_level = debug
+//! # Tried in order, see [ModePolicy]
+//! resolution = 1920x1080, 1280x720, native, largest
+//! cmdline = passed to the kernel as is
+//! # Name then path, can be repeated
+//! module = initrd \initrd.img
+//! # Randomizes where the kernel and the physical mapping go, on by default
+//! kaslr = off
+//! ```
+
+use crate::allocator::PreBootAllocator;
+use crate::framebuffer::MAX_MODE_POLICIES;
+use crate::framebuffer::ModePolicy;
+use crate::misc;
+use boot_protocol::module::MAX_MODULE_COUNT;
+use common::collections::smallvec::SmallVec;
+use core::str;
+use log::LevelFilter;
+use log::info;
+use log::warn;
+use uefi::boot::MemoryType;
+
+const CONFIG_FILENAME: &str = "pentos.cfg";
+
+pub struct BootConfig {
+    pub kernel: &'static str,
+    pub log_level: LevelFilter,
+    /// How to pick the GOP mode, first policy that matches wins
+    pub mode_policies: SmallVec<ModePolicy, MAX_MODE_POLICIES>,
+    pub cmdline: &'static str,
+    pub modules: SmallVec<ModuleConfig, MAX_MODULE_COUNT>,
+    pub kaslr: bool,
+}
+
+pub struct ModuleConfig {
+    pub name: &'static str,
+    pub path: &'static str,
+}
+
+impl BootConfig {
+    pub const fn new() -> Self {
+        Self {
+            kernel: "pentos.kernel",
+            log_level: log::STATIC_MAX_LEVEL,
+            mode_policies: SmallVec::new(),
+            cmdline: "",
+            modules: SmallVec::new(),
+            kaslr: true,
+        }
+    }
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Falls back to the defaults if the file is missing
+pub fn load(allocator: &PreBootAllocator) -> BootConfig {
+    let mut path_buf = [0u16; 256];
+    let Some(path) = misc::image_sibling(CONFIG_FILENAME, &mut path_buf) else {
+        warn!("Couldn't locate the bootloader image, using default configuration");
+        return BootConfig::new();
+    };
+    let Some(contents) = misc::read_boot_file(path, MemoryType::LOADER_DATA, allocator) else {
+        info!("No {CONFIG_FILENAME} found, using default configuration");
+        return BootConfig::new();
+    };
+    let Ok(contents) = str::from_utf8(contents) else {
+        warn!("{CONFIG_FILENAME} is not valid UTF-8, using default configuration");
+        return BootConfig::new();
+    };
+    parse(contents)
+}
+
+fn parse(contents: &'static str) -> BootConfig {
+    let mut config = BootConfig::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            warn!("{CONFIG_FILENAME}: Ignoring \"{line}\"");
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "kernel" => config.kernel = value,
+            "cmdline" => config.cmdline = value,
+            "log_level" => match value.parse() {
+                Ok(level) => config.log_level = level,
+                Err(_) => warn!("{CONFIG_FILENAME}: Unknown log level \"{value}\""),
+            },
+            "resolution" => {
+                config.mode_policies = SmallVec::new();
+                for policy in value.split(',').map(str::trim) {
+                    let Some(policy) = parse_mode_policy(policy) else {
+                        warn!("{CONFIG_FILENAME}: Invalid resolution \"{policy}\"");
+                        continue;
+                    };
+                    if config.mode_policies.push(policy).is_err() {
+                        warn!("{CONFIG_FILENAME}: Too many resolutions, ignoring the rest");
+                        break;
+                    }
+                }
+            }
+            "module" => match value.split_once(char::is_whitespace) {
+                Some((name, path)) => {
+                    let module = ModuleConfig {
+                        name,
+                        path: path.trim(),
+                    };
+                    if config.modules.push(module).is_err() {
+                        warn!("{CONFIG_FILENAME}: Too many modules, ignoring \"{name}\"");
+                    }
+                }
+                None => warn!("{CONFIG_FILENAME}: Module \"{value}\" has no path"),
+            },
+            "kaslr" => match value {
+                "on" => config.kaslr = true,
+                "off" => config.kaslr = false,
+                _ => warn!("{CONFIG_FILENAME}: kaslr is either on or off, not \"{value}\""),
+            },
+            key => warn!("{CONFIG_FILENAME}: Unknown key \"{key}\""),
+        }
+    }
+    config
+}
+
+fn parse_mode_policy(value: &str) -> Option<ModePolicy> {
+    match value {
+        "native" => Some(ModePolicy::Native),
+        "largest" => Some(ModePolicy::Largest),
+        value => {
+            let (width, height) = value.split_once('x')?;
+            Some(ModePolicy::Exact(
+                width.trim().parse().ok()?,
+                height.trim().parse().ok()?,
+            ))
+        }
+    }
+}
@@ -2,57 +2,101 @@ use crate::acpi;
 use crate::allocator::ALLOCATOR_CAP;
 use crate::allocator::PostBootAllocator;
 use crate::allocator::PreBootAllocator;
+use crate::allocator::reserve_superlowmem;
+use crate::bootconfig;
 use crate::bootstage;
 use crate::features;
 use crate::framebuffer;
+use crate::framebuffer::GopFramebufferInfo;
+use crate::infoarea::SYSINFO_AREA;
+use crate::kaslr;
 use crate::kernel;
 use crate::logger;
+use crate::module;
+use crate::panic;
+use crate::phys_mmap;
 use crate::phys_mmap::PhysMemMap;
 use crate::pic;
+use crate::smp;
+use crate::timing;
 use crate::topology;
 use crate::virt_mmap;
 use boot_protocol::BootInfo;
-use boot_protocol::MAX_MMAP_SIZE;
-use boot_protocol::OFFSET_MAPPING;
+use boot_protocol::BootInfoHeader;
+use boot_protocol::cmdline::CmdLine;
+use boot_protocol::cmdline::MAX_CMDLINE_LEN;
+use boot_protocol::mmap::MemoryKind;
+use boot_protocol::mmap::MemoryMap as BootMemoryMap;
+use boot_protocol::timing::BootPhase;
+use boot_protocol::timing::BootTimeline;
+use core::mem;
 use log::info;
+use log::warn;
 use uefi::Status;
 use uefi::boot;
 use uefi::boot::MemoryType;
 use uefi::entry;
 use uefi::mem::memory_map::MemoryMap as UefiMemoryMap;
 use uefi::system;
-use x64::mem::addr::Address;
-use x64::mem::PhysicalMemoryRegion;
 use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
 use x64::mem::addr::PhysAddr;
-use x64::mem::addr::VirtAddr;
 use x64::mem::page::Page;
 use x64::msr::efer::Efer;
 use x64::msr::pat::standard_pat;
 
 #[entry]
 fn main() -> Status {
+    timing::init();
     uefi::helpers::init().unwrap();
-    system::with_stdout(|stdout| {
-        // If it fails, we don't really care.
-        stdout.clear().ok();
-    });
+    if logger::console_available() {
+        system::with_stdout(|stdout| {
+            // If it fails, we don't really care.
+            stdout.clear().ok();
+        });
+    }
     unsafe {
         // SAFETY: We call logger::disable() before exiting UEFI boot services.
         logger::init();
     }
     info!("Booting PentOS...");
 
+    let phase = timing::begin(BootPhase::FeatureDetection);
     let features = features::bsp_featureset();
+    phase.end();
     let allocator = PreBootAllocator;
-    acpi::init();
-    let kernel = kernel::load_kernel(&allocator);
+    let phase = timing::begin(BootPhase::ConfigLoad);
+    let config = bootconfig::load(&allocator);
+    log::set_max_level(config.log_level);
+    // Dropped rather than cut, which could change what the last argument means
+    let cmdline = CmdLine::new(config.cmdline).unwrap_or_else(|| {
+        warn!("Kernel command line longer than {MAX_CMDLINE_LEN} bytes, ignoring it");
+        CmdLine::empty()
+    });
+    phase.end();
+    let phase = timing::begin(BootPhase::AcpiParsing);
+    let acpi = acpi::init();
+    let serial = acpi::serial_port(&acpi);
+    phase.end();
+    let phase = timing::begin(BootPhase::KernelLoad);
+    let kernel = kernel::load_kernel(&allocator, config.kernel);
+    let kernel_meta = kernel::kernel_meta(&kernel, &features);
+    let entropy = config.kaslr.then(kaslr::Entropy::seed);
+    phase.end();
+    let phase = timing::begin(BootPhase::ModuleLoad);
+    let loaded_modules = module::load_modules(&config, &allocator);
+    phase.end();
+    reserve_superlowmem();
 
     topology::dump();
 
     // Keep this last in PreBootStage
-    let primary_framebuffer_info = framebuffer::init();
+    let phase = timing::begin(BootPhase::FramebufferInit);
+    let gop_framebuffers = framebuffer::init(&config.mode_policies);
+    phase.end();
 
+    let phase = timing::begin(BootPhase::ExitBootServices);
     logger::disable();
     bootstage::set_postboot();
     // TODO: AP wait_for_config
@@ -60,26 +104,30 @@ fn main() -> Status {
         // SAFETY: Only thing we used was the UEFI console logger, and allocator, they are now disabled
         boot::exit_boot_services(MemoryType::LOADER_DATA)
     };
-    
+    phase.end();
+
+    logger::attach_serial(&serial);
+    if let Some(framebuffer) = gop_framebuffers.first() {
+        panic::set_screen(unsafe {
+            // SAFETY: The firmware page tables are still loaded
+            framebuffer.identity_panic_screen()
+        });
+    }
     pic::disable();
+    timing::calibrate();
 
+    let phase = timing::begin(BootPhase::PageTables);
     let mut mmap = PhysMemMap::<ALLOCATOR_CAP>::new();
-    let mut loader_mmap = PhysMemMap::<64>::new();
     for entry in real_mmap.entries() {
         let region = PhysicalMemoryRegion::new(
             PhysAddr::new_panic(entry.phys_start as usize),
             MemorySize::new(entry.page_count as usize * 4096),
         );
-        if entry.phys_start >= 1024 * 1024 && (entry.ty == MemoryType::CONVENTIONAL) {
-            mmap.add(region);
-        }
         if entry.phys_start >= 1024 * 1024
-            && (entry.ty == MemoryType::LOADER_CODE
-                || entry.ty == MemoryType::LOADER_DATA
-                || entry.ty == MemoryType::BOOT_SERVICES_CODE
-                || entry.ty == MemoryType::BOOT_SERVICES_DATA)
+            && entry.ty == MemoryType::CONVENTIONAL
+            && mmap.add(region).is_err()
         {
-            loader_mmap.add(region);
+            panic!("Too many conventional memory regions");
         }
     }
 
@@ -90,31 +138,81 @@ fn main() -> Status {
 
     Efer::new().syscall(false).exec_disable(true).write();
     standard_pat().write();
+    let kaslr = kaslr::layout(
+        entropy,
+        kernel::kernel_span(&kernel),
+        virt_mmap::offset_mapping_end(&real_mmap, &acpi),
+    );
     let root_map =
-        virt_mmap::identity_and_offset_mapping(&mut allocator, &real_mmap, OFFSET_MAPPING);
-    kernel::map_kernel(&kernel, root_map, &mut allocator);
-    let framebuffer =
-        framebuffer::postboot_init(primary_framebuffer_info, root_map, &mut allocator);
+        virt_mmap::identity_and_offset_mapping(&mut allocator, &real_mmap, kaslr.offset_mapping);
+    let kernel_image = kernel::map_kernel(&kernel, kaslr.kernel_slide, root_map, &mut allocator);
+    let framebuffers = framebuffer::postboot_init(&gop_framebuffers, root_map, &mut allocator);
+    virt_mmap::map_rsdp(&acpi, root_map, &mut allocator, kaslr.offset_mapping);
+    let stacks = kernel::alloc_stacks(root_map, &mut allocator);
+    let tls = kernel::alloc_tls(&kernel, kernel_image, root_map, &mut allocator);
+    let modules = module::map_modules(&loaded_modules, root_map, &mut allocator);
+    let log = logger::map_boot_log(root_map, &mut allocator);
     let bootinfo = BootInfo {
-        mmap: [PhysicalMemoryRegion::null(); MAX_MMAP_SIZE],
-        mmap_len: 0,
+        header: BootInfoHeader::new(),
+        mmap: BootMemoryMap::new(),
         features,
-        framebuffer,
+        framebuffers,
+        stacks,
+        tls,
+        topology: topology::topology().clone(),
+        acpi,
+        cmdline,
+        modules,
+        serial,
+        log,
+        timeline: BootTimeline::new(0),
+        kaslr,
     };
     let bootinfo = allocator
-        .alloc(bootinfo)
+        .alloc(bootinfo, MemoryKind::BootInfo)
         .expect("Failed to allocate bootinfo");
+    let bootinfo_virt = SYSINFO_AREA.allocate(mem::size_of::<BootInfo>());
     virt_mmap::map_bootinfo(
         bootinfo,
-        Page::containing(VirtAddr::new_panic(OFFSET_MAPPING)),
+        Page::containing(bootinfo_virt),
         root_map,
         &mut allocator,
     );
-    let stack = kernel::alloc_stack(root_map, &mut allocator);
+    let trampoline = smp::prepare(root_map, &mut allocator);
+    phase.end();
+    let phase = timing::begin(BootPhase::ApStartup);
+    // The framebuffer is not identity mapped in root_map
+    panic::clear_screen();
     root_map.load();
-    let mmap = allocator.fini(loader_mmap);
-    bootinfo.mmap = mmap.regions;
-    bootinfo.mmap_len = mmap.len;
+    if let Some(framebuffer) = bootinfo.framebuffer() {
+        unsafe {
+            // SAFETY: Mapped in root_map, the kernel only gets it once we cede control
+            logger::attach_console(framebuffer);
+        }
+    }
+    info!("Kernel page tables loaded, starting APs");
+    smp::start_aps(&trampoline, &mut allocator);
+    phase.end();
+    let phase = timing::begin(BootPhase::MemoryMap);
+    let (free, used) = allocator.fini();
+    if let Err(entry) = phys_mmap::typed_memory_map(
+        &mut bootinfo.mmap,
+        &real_mmap,
+        &free,
+        &used,
+        gop_framebuffers.iter().map(GopFramebufferInfo::region),
+        trampoline.region(),
+    ) {
+        panic!("Memory map too big, {:?} does not fit", entry);
+    }
+    phase.end();
 
-    kernel::bsp_cede_control(&kernel, stack);
+    bootinfo.timeline = timing::cede_control();
+    kernel::bsp_cede_control(
+        &kernel_meta,
+        bootinfo.kaslr.kernel_slide,
+        &bootinfo.stacks,
+        &bootinfo.tls,
+        bootinfo_virt,
+    );
 }
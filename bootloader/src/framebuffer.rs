@@ -1,26 +1,35 @@
 use crate::allocator::ALLOCATOR_CAP;
 use crate::allocator::PostBootAllocator;
-use crate::infoarea::allocate_info_space;
+use crate::infoarea::GLOBAL_MMIO_AREA;
+use crate::panic::PanicScreen;
 use crate::virt_mmap::map;
+use boot_protocol::ffi::RawSlice;
 use boot_protocol::framebuffer::FramebufferInfo;
-use x64::mem::addr::Address;
-use core::mem;
+use boot_protocol::framebuffer::MAX_FRAMEBUFFER_COUNT;
+use boot_protocol::mmap::MemoryKind;
+use common::collections::smallvec::SmallVec;
 use core::slice;
+use log::debug;
+use log::info;
+use log::warn;
 use uefi::Identify;
 use uefi::boot;
 use uefi::boot::SearchType;
 use uefi::proto::console::gop::GraphicsOutput;
 use uefi::proto::console::gop::Mode;
 use uefi::proto::console::gop::PixelFormat;
+use x64::framebuffer::PixelBitmask;
 use x64::framebuffer::PixelMode;
 use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
 use x64::mem::addr::PhysAddr;
 use x64::mem::frame::Frame;
 use x64::mem::page::Page;
 use x64::mem::paging::PagingRootEntry;
 use x64::msr::pat::MemoryType;
 
-pub struct PrimaryFramebufferInfo {
+pub struct GopFramebufferInfo {
     base: PhysAddr,
     size: MemorySize,
     width: usize,
@@ -29,79 +38,209 @@ pub struct PrimaryFramebufferInfo {
     mode: PixelMode,
 }
 
-pub fn init() -> PrimaryFramebufferInfo {
-    let handle = *boot::locate_handle_buffer(SearchType::ByProtocol(&GraphicsOutput::GUID))
-        .expect("Failed to locate GOP")
-        .first()
-        .expect("No GOP found");
-    let mut gop =
-        boot::open_protocol_exclusive::<GraphicsOutput>(handle).expect("Couldn't open GOP");
+pub type GopFramebuffers = SmallVec<GopFramebufferInfo, MAX_FRAMEBUFFER_COUNT>;
 
-    let best_mode = gop
-        .modes()
-        .filter(|mode| {
-            mode.info().pixel_format() == PixelFormat::Bgr
-                || mode.info().pixel_format() == PixelFormat::Rgb
-        })
-        .fold(None, |best_mode: Option<Mode>, this_mode| {
-            if best_mode.is_none_or(|best_mode| {
-                let (best_width, best_height) = best_mode.info().resolution();
-                let (this_width, this_height) = this_mode.info().resolution();
-                let best_area = best_width * best_height;
-                let this_area = this_width * this_height;
-                best_area > this_area
-            }) {
-                Some(this_mode)
-            } else {
-                Some(best_mode.unwrap())
-            }
-        });
+pub const MAX_MODE_POLICIES: usize = 8;
 
-    let Some(best_mode) = best_mode else {
-        panic!("No suitable GOP mode found");
-    };
-    gop.set_mode(&best_mode).expect("Couldn't set GOP mode");
-    let info = best_mode.info();
-    let (width, height) = info.resolution();
-    let stride = info.stride();
+/// One way of picking a GOP mode, from the boot config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModePolicy {
+    /// The mode the firmware left the output in, usually the display's native resolution
+    Native,
+    /// The mode with the most pixels
+    Largest,
+    /// Width then height
+    Exact(usize, usize),
+}
 
-    let mut fb = gop.frame_buffer();
-    let base = PhysAddr::new_panic(fb.as_mut_ptr() as usize);
-    let size = MemorySize::new(fb.size());
+/// Tried after the configured policies
+const FALLBACK_MODE_POLICIES: [ModePolicy; 2] = [ModePolicy::Native, ModePolicy::Largest];
 
-    PrimaryFramebufferInfo {
-        base,
-        size,
-        width,
-        height,
-        stride,
-        mode: match info.pixel_format() {
-            PixelFormat::Bgr => PixelMode::BgrRs,
-            PixelFormat::Rgb => PixelMode::RgbRs,
-            _ => unimplemented!("Unsupported pixel format"),
-        },
+impl GopFramebufferInfo {
+    /// Physical memory backing the framebuffer
+    pub fn region(&self) -> PhysicalMemoryRegion {
+        PhysicalMemoryRegion::new(self.base, self.size)
+    }
+    /// # Safety
+    /// Only valid while the firmware page tables, which identity map the framebuffer, are loaded
+    pub unsafe fn identity_panic_screen(&self) -> PanicScreen {
+        unsafe {
+            // SAFETY: Guaranteed by the caller
+            PanicScreen::new(
+                *self.base as *mut u8,
+                *self.size,
+                (self.width, self.height),
+                self.stride,
+                self.mode,
+            )
+        }
     }
 }
 
+/// Sets up every GOP output, the primary one first.
+/// Each output gets the mode of the first of `policies` it can satisfy.
+/// Empty if there is no usable GOP, we boot headless then.
+pub fn init(policies: &[ModePolicy]) -> GopFramebuffers {
+    let mut framebuffers = GopFramebuffers::new();
+    let Ok(handles) = boot::locate_handle_buffer(SearchType::ByProtocol(&GraphicsOutput::GUID))
+    else {
+        warn!("No GOP found, booting headless");
+        return framebuffers;
+    };
+    for &handle in handles.iter() {
+        let Ok(mut gop) = boot::open_protocol_exclusive::<GraphicsOutput>(handle) else {
+            warn!("Couldn't open GOP {:?}, skipping it", handle);
+            continue;
+        };
+        // Some firmwares expose the same display through several handles, which share the
+        // framebuffer of the mode already set through the first one
+        let current = PhysAddr::new_panic(gop.frame_buffer().as_mut_ptr() as usize);
+        if framebuffers.iter().any(|other| other.base == current) {
+            debug!(
+                "GOP {:?} is a duplicate of framebuffer {:?}, skipping it",
+                handle, current
+            );
+            continue;
+        }
+        let Some(mode) = select_mode(&gop, policies) else {
+            warn!("No suitable mode on GOP {:?}, skipping it", handle);
+            continue;
+        };
+        if let Err(err) = gop.set_mode(&mode) {
+            warn!(
+                "Couldn't set mode on GOP {:?}: {:?}, skipping it",
+                handle,
+                err.status()
+            );
+            continue;
+        }
+        let info = mode.info();
+        let (width, height) = info.resolution();
+        let mut fb = gop.frame_buffer();
+        let base = PhysAddr::new_panic(fb.as_mut_ptr() as usize);
+        let framebuffer = GopFramebufferInfo {
+            base,
+            size: MemorySize::new(fb.size()),
+            width,
+            height,
+            stride: info.stride(),
+            mode: match info.pixel_format() {
+                PixelFormat::Bgr => PixelMode::BgrRs,
+                PixelFormat::Rgb => PixelMode::RgbRs,
+                PixelFormat::Bitmask => {
+                    let mask = info.pixel_bitmask().unwrap();
+                    PixelMode::Bitmask(PixelBitmask {
+                        red: mask.red,
+                        green: mask.green,
+                        blue: mask.blue,
+                        reserved: mask.reserved,
+                    })
+                }
+                PixelFormat::BltOnly => unreachable!("Filtered out in select_mode"),
+            },
+        };
+        info!(
+            "Framebuffer {}: {}x{} at {:?}",
+            framebuffers.len(),
+            width,
+            height,
+            base
+        );
+        if framebuffers.push(framebuffer).is_err() {
+            warn!("Too many framebuffers, ignoring the remaining GOPs");
+            break;
+        }
+    }
+    if framebuffers.is_empty() {
+        warn!("No usable GOP, booting headless");
+    }
+    framebuffers
+}
+
+fn select_mode(gop: &GraphicsOutput, policies: &[ModePolicy]) -> Option<Mode> {
+    // An all zero bitmask would make pixels 0 bytes wide
+    let supported = |mode: &Mode| match mode.info().pixel_format() {
+        PixelFormat::BltOnly => false,
+        PixelFormat::Bitmask => mode
+            .info()
+            .pixel_bitmask()
+            .is_some_and(|mask| mask.red | mask.green | mask.blue | mask.reserved != 0),
+        _ => true,
+    };
+    for (i, mode) in gop.modes().enumerate() {
+        let (width, height) = mode.info().resolution();
+        debug!(
+            "GOP mode {}: {}x{} {:?}",
+            i,
+            width,
+            height,
+            mode.info().pixel_format()
+        );
+    }
+    let current = gop.current_mode_info();
+    for &policy in policies.iter().chain(&FALLBACK_MODE_POLICIES) {
+        let mode = match policy {
+            ModePolicy::Native => gop
+                .modes()
+                .filter(supported)
+                .find(|mode| *mode.info() == current),
+            ModePolicy::Largest => gop.modes().filter(supported).max_by_key(|mode| {
+                let (width, height) = mode.info().resolution();
+                width * height
+            }),
+            ModePolicy::Exact(width, height) => gop
+                .modes()
+                .filter(supported)
+                .find(|mode| mode.info().resolution() == (width, height)),
+        };
+        match mode {
+            Some(mode) => return Some(mode),
+            None => info!(
+                "GOP mode policy {:?} not satisfied, trying the next one",
+                policy
+            ),
+        }
+    }
+    None
+}
+
 pub fn postboot_init(
-    primary: PrimaryFramebufferInfo,
+    framebuffers: &GopFramebuffers,
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+) -> SmallVec<FramebufferInfo, MAX_FRAMEBUFFER_COUNT> {
+    let mut infos = SmallVec::new();
+    for framebuffer in framebuffers {
+        if infos
+            .push(map_framebuffer(framebuffer, root_map, allocator))
+            .is_err()
+        {
+            unreachable!("As many GOP framebuffers as there is room for");
+        }
+    }
+    infos
+}
+
+fn map_framebuffer(
+    gop: &GopFramebufferInfo,
     root_map: PagingRootEntry,
     allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
 ) -> FramebufferInfo {
     let buffer = allocator
-        .alloc_raw(*primary.size, 0x1000)
+        .alloc_raw(*gop.size, 0x1000, MemoryKind::BootInfo)
         .expect("Out of memory");
     let buffer_frame_start = Frame::containing(buffer);
-    let buffer = unsafe {
+    unsafe {
         // SAFETY: trust in the process
-        slice::from_raw_parts_mut(buffer.as_mut_ptr(), *primary.size / mem::size_of::<u32>())
-    };
-    buffer.fill(0);
+        slice::from_raw_parts_mut(buffer.as_mut_ptr::<u8>(), *gop.size)
+    }
+    .fill(0);
 
-    let fb = allocate_info_space(*primary.size);
-    let buffer_page_start = Page::containing(allocate_info_space(*primary.size));
-    let pg_count = primary.size.next_multiple_of(0x1000) / 0x1000;
-    let fb_frame_start = Frame::containing(primary.base);
+    let fb = GLOBAL_MMIO_AREA.allocate(*gop.size);
+    let buffer = GLOBAL_MMIO_AREA.allocate(*gop.size);
+    let buffer_page_start = Page::containing(buffer);
+    let pg_count = gop.size.next_multiple_of(0x1000) / 0x1000;
+    let fb_frame_start = Frame::containing(gop.base);
     let fb_page_start = Page::containing(fb);
 
     for i in 0..pg_count {
@@ -124,16 +263,22 @@ pub fn postboot_init(
             MemoryType::WriteBack,
         );
     }
-    let fb = unsafe {
-        // SAFETY: trust in the process
-        slice::from_raw_parts_mut(fb.as_mut_ptr(), *primary.size / mem::size_of::<u32>())
+    let (fb, buffer) = unsafe {
+        // SAFETY: trust in the process, both are mapped in `root_map` for the kernel
+        (
+            slice::from_raw_parts_mut(fb.as_mut_ptr(), *gop.size),
+            slice::from_raw_parts_mut(buffer.as_mut_ptr(), *gop.size),
+        )
     };
 
     FramebufferInfo {
-        fb,
-        width: primary.width,
-        height: primary.height,
-        stride: primary.stride,
-        buffer,
+        phys: gop.base,
+        fb: RawSlice::new(fb),
+        width: gop.width,
+        height: gop.height,
+        stride: gop.stride,
+        mode: gop.mode,
+        bytes_per_pixel: gop.mode.bytes_per_pixel(),
+        buffer: RawSlice::new(buffer),
     }
 }
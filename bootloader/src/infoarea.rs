@@ -1,17 +1,40 @@
+use config::vmem::GLOBAL_MMIO_REGION;
+use config::vmem::SYSINFO_REGION;
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering;
-
-use x64::mem::addr::Address;
+use x64::mem::VirtualMemoryRegion;
 use x64::mem::addr::VirtAddr;
 
-static INFO_AREA: AtomicUsize = AtomicUsize::new(0xfffffff000000000);
+/// BootInfo, the boot log and modules
+pub static SYSINFO_AREA: VirtArea = VirtArea::new("sysinfo", SYSINFO_REGION);
+/// Framebuffers, along with their back buffers
+pub static GLOBAL_MMIO_AREA: VirtArea = VirtArea::new("global MMIO", GLOBAL_MMIO_REGION);
+
+/// Hands out page aligned virtual memory from one of the [config::vmem] regions, never given back
+pub struct VirtArea {
+    name: &'static str,
+    region: VirtualMemoryRegion,
+    /// Offset of the next allocation in `region`
+    next: AtomicUsize,
+}
 
-pub fn allocate_info_space(size: usize) -> VirtAddr {
-    let size = size.next_multiple_of(0x1000);
-    let addr = INFO_AREA.fetch_add(size, Ordering::Relaxed);
-    if addr + size > 0xfffffffc00000000 {
-        panic!("Out of memory for info area");
+impl VirtArea {
+    pub const fn new(name: &'static str, region: VirtualMemoryRegion) -> Self {
+        Self {
+            name,
+            region,
+            next: AtomicUsize::new(0),
+        }
     }
+}
 
-    VirtAddr::new_panic(addr)
+impl VirtArea {
+    pub fn allocate(&self, size: usize) -> VirtAddr {
+        let size = size.next_multiple_of(0x1000);
+        let offset = self.next.fetch_add(size, Ordering::Relaxed);
+        if offset + size > *self.region.size() {
+            panic!("Out of memory for the {} area", self.name);
+        }
+        self.region.start() + offset
+    }
 }
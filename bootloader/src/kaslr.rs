@@ -0,0 +1,88 @@
+//! Kernel address space layout randomization, see [KaslrInfo]
+
+use boot_protocol::kaslr::KaslrInfo;
+use config::vmem::KBIN_REGION;
+use config::vmem::MAX_PHYS_SPACE;
+use config::vmem::PHYSICAL_MAPPING_REGION;
+use log::info;
+use log::warn;
+use uefi::boot;
+use uefi::proto::rng::Rng;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+use x64::rand;
+use x64::tsc;
+
+/// Kernel slides keep 2M pages of the kernel aligned
+const KERNEL_SLIDE_ALIGN: usize = 0x20_0000;
+/// Offset mapping slides keep its 1G pages aligned
+const OFFSET_SLIDE_ALIGN: usize = 0x4000_0000;
+
+/// Random state for [layout], seeded before exiting boot services
+pub struct Entropy {
+    state: u64,
+}
+
+impl Entropy {
+    /// Seeds from the UEFI RNG protocol, falling back to RDRAND, then to the TSC
+    pub fn seed() -> Self {
+        let state = uefi_rng().or_else(rand::rdrand).unwrap_or_else(|| {
+            warn!("No random number source, KASLR falls back to the TSC");
+            tsc::read()
+        });
+        Self { state }
+    }
+    /// SplitMix64
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    /// Uniform enough in `0..bound`, `bound` must not be 0
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+fn uefi_rng() -> Option<u64> {
+    let handle = boot::get_handle_for_protocol::<Rng>().ok()?;
+    let mut rng = boot::open_protocol_exclusive::<Rng>(handle).ok()?;
+    let mut seed = [0; 8];
+    rng.get_rng(None, &mut seed).ok()?;
+    Some(u64::from_ne_bytes(seed))
+}
+
+/// Slides `kernel`, linked in [KBIN_REGION], and the offset mapping of physical memory
+/// below `phys_end` to random places in their regions, nothing moves without `entropy`
+pub fn layout(
+    entropy: Option<Entropy>,
+    kernel: VirtualMemoryRegion,
+    phys_end: PhysAddr,
+) -> KaslrInfo {
+    let Some(mut entropy) = entropy else {
+        info!("KASLR disabled");
+        return KaslrInfo {
+            kernel_slide: 0,
+            offset_mapping: PHYSICAL_MAPPING_REGION.start().as_usize(),
+        };
+    };
+    let kernel_room = KBIN_REGION.end().as_usize() - kernel.end().as_usize();
+    let kernel_slide = entropy.below(kernel_room / KERNEL_SLIDE_ALIGN + 1) * KERNEL_SLIDE_ALIGN;
+    let phys_room = MAX_PHYS_SPACE
+        .as_usize()
+        .saturating_sub(phys_end.as_usize().next_multiple_of(OFFSET_SLIDE_ALIGN));
+    let offset_slide = entropy.below(phys_room / OFFSET_SLIDE_ALIGN + 1) * OFFSET_SLIDE_ALIGN;
+    let kaslr = KaslrInfo {
+        kernel_slide,
+        offset_mapping: PHYSICAL_MAPPING_REGION.start().as_usize() + offset_slide,
+    };
+    info!(
+        "KASLR: kernel at {:#x}, physical memory at {:#x}",
+        kernel.start().as_usize() + kaslr.kernel_slide,
+        kaslr.offset_mapping
+    );
+    kaslr
+}
@@ -1,179 +1,348 @@
 use crate::allocator::ALLOCATOR_CAP;
 use crate::allocator::PostBootAllocator;
 use crate::allocator::PreBootAllocator;
-use crate::infoarea::allocate_info_space;
 use crate::misc;
+use crate::topology;
 use crate::virt_mmap;
-use boot_protocol::STACK_SIZE;
+use boot_protocol::BOOT_PROTOCOL_VERSION;
+use boot_protocol::features::FeatureSet;
+use boot_protocol::kernel_meta::KERNEL_META_NOTE_NAME;
+use boot_protocol::kernel_meta::KERNEL_META_NOTE_TYPE;
 use boot_protocol::kernel_meta::KernelMeta;
+use boot_protocol::mmap::MemoryKind;
+use boot_protocol::topology::HartStack;
+use boot_protocol::topology::HartTls;
+use common::collections::smallvec::SmallVec;
+use config::topology::hart::KSTACK_GUARD_SIZE;
+use config::topology::hart::KSTACK_SIZE;
+use config::topology::hart::KTLS_GUARD_SIZE;
+use config::topology::hart::MAX_HART_COUNT;
+use config::vmem::KBIN_REGION;
+use config::vmem::KSTACK_REGION;
+use config::vmem::KTLS_REGION;
 use core::arch::asm;
-use core::cmp::max;
 use core::hint;
-use core::mem;
+use core::slice;
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering;
 use elf::Elf;
-use elf::ElfClass;
 use elf::ElfType;
 use elf::SegmentType;
+use elf::loader::Loader;
+use elf::loader::PAGE_SIZE;
+use elf::loader::PageMapping;
+use elf::tls::TlsTemplate;
+use log::error;
 use spinlocks::once::Once;
 use uefi::CStr16;
-use uefi::Identify;
 use uefi::boot;
-use uefi::boot::SearchType;
-use uefi::proto::media::file::File;
-use uefi::proto::media::file::FileAttribute;
-use uefi::proto::media::file::FileMode;
-use uefi::proto::media::fs::SimpleFileSystem;
 use x64::lapic;
+use x64::mem::MemorySize;
+use x64::mem::VirtualMemoryRegion;
 use x64::mem::addr::Address;
-use x64::mem::addr::PhysAddr;
 use x64::mem::addr::VirtAddr;
 use x64::mem::frame::Frame;
 use x64::mem::page::Page;
 use x64::mem::paging::PagingRootEntry;
+use x64::msr::fs_base::FsBase;
 use x64::msr::pat::MemoryType;
 
 struct ApInfo {
     pub ap_entry: VirtAddr,
-    pub stack_base: VirtAddr,
+    pub stacks: &'static [HartStack],
+    pub tls: &'static [HartTls],
+    pub bootinfo: VirtAddr,
 }
 
 static AP_CEDE: Once<ApInfo> = Once::new();
 static AP_REMAINING: AtomicUsize = AtomicUsize::new(0);
 
 // TODO: Load kernel from PentFS partition
-pub fn load_kernel(allocator: &PreBootAllocator) -> Elf<'static> {
-    let simple_fs_handle =
-        *boot::locate_handle_buffer(SearchType::ByProtocol(&SimpleFileSystem::GUID))
-            .expect("Failed to locate SimpleFileSystem protocol")
-            .first()
-            .expect("No SimpleFileSystem protocol found");
-    let mut simple_fs = boot::open_protocol_exclusive::<SimpleFileSystem>(simple_fs_handle)
-        .expect("Failed to open SimpleFileSystem protocol");
-    let mut volume = simple_fs.open_volume().expect("Failed to open volume");
-
-    let filename = "pentos.kernel";
+pub fn load_kernel(allocator: &PreBootAllocator, filename: &str) -> Elf<'static> {
     let mut file_buf = [0u16; 256];
     let filename_wide =
         CStr16::from_str_with_buf(filename, &mut file_buf).expect("Filename too long");
-    let kernel_file = volume
-        .open(filename_wide, FileMode::Read, FileAttribute::empty())
-        .expect("Failed to open kernel file");
-    let mut kernel_file = kernel_file
-        .into_regular_file()
-        .expect("Kernek file is not a regular file");
-
-    let file_size =
-        misc::get_file_size(&mut kernel_file, allocator).expect("Failed to get kernel file size");
-    let buffer = allocator
-        .alloc_slice(file_size, 0u8, boot::MemoryType::LOADER_DATA)
-        .expect("Failed to allocate buffer for kernel file");
-    kernel_file
-        .read(buffer)
+    let buffer = misc::read_boot_file(filename_wide, boot::MemoryType::LOADER_DATA, allocator)
         .expect("Failed to read kernel file");
-    let elf = Elf::parse(buffer).expect("Failed to parse kernel");
-    if elf.ty != ElfType::Executable {
+    // Only 64-bit little endian files get through
+    let elf = match Elf::parse(buffer) {
+        Ok(elf) => elf,
+        Err(error) => panic!("Kernel file {filename} is corrupt: {error:?}"),
+    };
+    // Position independent kernels are shared objects
+    if elf.ty != ElfType::Executable && elf.ty != ElfType::SharedObject {
         panic!("Kernel is not an executable");
     }
-    if elf.ident.encoding != elf::DataEncoding::LittleEndian {
-        panic!("Kernel is not little endian");
+    let loader = match Loader::new(&elf) {
+        Ok(loader) => loader,
+        Err(error) => panic!("Kernel cannot be loaded: {error:?}"),
+    };
+    if let Err(error) = elf.relocations() {
+        panic!("Kernel relocations cannot be read: {error:?}");
+    }
+    for segment in &elf.program_header {
+        if segment.ty != SegmentType::Load {
+            continue;
+        }
+        let region = VirtualMemoryRegion::new(segment.vaddr, segment.mem_size);
+        if !KBIN_REGION.contains_region(region) {
+            panic!(
+                "Kernel segment at {:#x} is outside of the kernel binary region",
+                segment.vaddr.as_usize()
+            );
+        }
     }
-    if elf.ident.class != ElfClass::Elf64 {
-        panic!("Kernel is not 64-bit");
+    let tls_segments = elf
+        .program_header
+        .into_iter()
+        .filter(|segment| segment.ty == SegmentType::Tls)
+        .count();
+    if tls_segments > 1 {
+        panic!("Kernel has more than one TLS segment");
+    }
+    if tls_segments == 1 {
+        let Some(template) = elf.tls() else {
+            panic!("Kernel TLS segment is corrupt");
+        };
+        // Blocks are page aligned
+        if template.block_alignment() > PAGE_SIZE {
+            panic!("Kernel TLS segment is aligned to more than a page");
+        }
+        let data = VirtualMemoryRegion::new(
+            VirtAddr::new_panic(template.vaddr),
+            MemorySize::new(template.data.len()),
+        );
+        if !loader.span().contains_region(data) {
+            panic!("Kernel TLS template is not loaded");
+        }
     }
 
     elf
 }
 
+/// Link time addresses of the image [map_kernel] maps
+pub fn kernel_span(kernel: &Elf<'static>) -> VirtualMemoryRegion {
+    Loader::new(kernel)
+        .expect("Kernel was checked when loaded")
+        .span()
+}
+
+/// Maps `kernel` `slide` bytes above where it was linked, relocated accordingly.
+/// The image is physically contiguous, and returned as [kernel_span] lays it out.
 pub fn map_kernel(
     kernel: &Elf<'static>,
+    slide: usize,
     root_map: PagingRootEntry,
     allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
-) {
-    for segment in &kernel.program_header {
-        if segment.ty == SegmentType::Load {
-            let pg_count = segment.mem_size.next_multiple_of(4096) / 4096;
-            let mut copied = 0;
-            for i in 0..pg_count {
-                let frame = Frame::containing(PhysAddr::new_panic(
-                    allocator.alloc([0; 4096]).expect("Out of memory") as *const _ as usize,
-                ));
-                if copied < segment.file_size {
-                    let src = kernel.data.as_ptr() as u64 + segment.offset + copied as u64;
-                    let dst = frame.boundary();
-                    unsafe {
-                        // SAFETY: We are copying from a valid memory region to a valid memory region
-                        core::ptr::copy_nonoverlapping(
-                            src as *const u8,
-                            dst.as_mut_ptr(),
-                            max(segment.file_size - copied, 4096),
-                        );
-                    }
-                    copied += max(segment.file_size - copied, 4096);
-                }
-                let page = Page::containing(segment.vaddr + i * 4096);
-                virt_mmap::map(
-                    root_map,
-                    allocator,
-                    frame,
-                    page,
-                    segment.flags.write,
-                    segment.flags.exec,
-                    MemoryType::WriteBack,
-                );
-            }
+) -> &'static [u8] {
+    let loader = Loader::new(kernel).expect("Kernel was checked when loaded");
+    let span = loader.span();
+    let image_start = allocator
+        .alloc_raw(*span.size(), PAGE_SIZE, MemoryKind::KernelImage)
+        .expect("Out of memory");
+    let image = unsafe {
+        // SAFETY: Just allocated, and physical memory is identity mapped
+        slice::from_raw_parts_mut(image_start.as_mut_ptr::<u8>(), *span.size())
+    };
+    let offset =
+        |mapping: &PageMapping| mapping.page.boundary().as_usize() - span.start().as_usize();
+
+    for mapping in loader.pages() {
+        let offset = offset(&mapping);
+        let dst = (&mut image[offset..offset + PAGE_SIZE])
+            .try_into()
+            .expect("Exactly a page");
+        loader.fill(mapping.page, dst);
+    }
+    let relocations = kernel
+        .relocations()
+        .expect("Kernel was checked when loaded");
+    if let Err(error) = relocations.apply(image, span.start().as_usize(), slide) {
+        panic!("Failed to relocate the kernel: {error:?}");
+    }
+    for mapping in loader.pages() {
+        virt_mmap::map(
+            root_map,
+            allocator,
+            Frame::containing(image_start + offset(&mapping)),
+            Page::containing(mapping.page.boundary() + slide),
+            mapping.flags.write,
+            mapping.flags.exec,
+            MemoryType::WriteBack,
+        );
+    }
+    image
+}
+
+/// Maps a main kernel stack for every registered hart in [KSTACK_REGION], side by side,
+/// each preceded by an unmapped guard
+pub fn alloc_stacks(
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+) -> SmallVec<HartStack, MAX_HART_COUNT> {
+    let mut stacks = SmallVec::new();
+    let pg_count = KSTACK_SIZE.next_multiple_of(4096) / 4096;
+    let slot_size = KSTACK_GUARD_SIZE + pg_count * 4096;
+
+    for (i, hart) in topology::topology().harts.iter().enumerate() {
+        let region = VirtualMemoryRegion::new(
+            KSTACK_REGION.start() + i * slot_size + KSTACK_GUARD_SIZE,
+            MemorySize::new(pg_count * 4096),
+        );
+        if !KSTACK_REGION.contains_region(region) {
+            panic!("Kernel stacks do not fit in their region");
+        }
+        let stack = Page::containing(region.start());
+        for i in 0..pg_count {
+            let frame = Frame::containing(
+                allocator
+                    .alloc_raw(0x1000, 0x1000, MemoryKind::KernelStack)
+                    .expect("Out of memory"),
+            );
+            virt_mmap::map(
+                root_map,
+                allocator,
+                frame,
+                stack + i,
+                true,
+                false,
+                MemoryType::WriteBack,
+            );
         }
+        // Topology and stacks have the same capacity
+        let _ = stacks.push(HartStack {
+            apic_id: hart.apic_id,
+            region,
+        });
     }
+    stacks
 }
 
-pub fn alloc_stack(
+/// Maps a kernel TLS block for every registered hart in [KTLS_REGION], side by side, each
+/// preceded by an unmapped guard and initialized from the relocated `image` [map_kernel] returned.
+/// Harts get a block with only a thread control block if the kernel has no TLS segment.
+pub fn alloc_tls(
+    kernel: &Elf<'static>,
+    image: &'static [u8],
     root_map: PagingRootEntry,
     allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
-) -> VirtAddr {
-    let stack = Page::containing(allocate_info_space(STACK_SIZE));
-    let pg_count = STACK_SIZE.next_multiple_of(4096) / 4096;
-    for i in 0..pg_count {
-        let frame = Frame::containing(allocator.alloc_raw(0x1000, 0x1000).expect("Out of memory"));
-        let page = stack + i;
-        virt_mmap::map(
-            root_map,
-            allocator,
-            frame,
-            page,
-            true,
-            false,
-            MemoryType::WriteBack,
+) -> SmallVec<HartTls, MAX_HART_COUNT> {
+    let mut template = kernel.tls().unwrap_or(TlsTemplate::EMPTY);
+    if !template.data.is_empty() {
+        // Pointers in the file are not relocated
+        let start = template.vaddr - kernel_span(kernel).start().as_usize();
+        template.data = &image[start..start + template.data.len()];
+    }
+    let mut blocks = SmallVec::new();
+    let size = template.block_size().next_multiple_of(PAGE_SIZE);
+    // PT_TLS may ask for more than page alignment
+    let align = template.block_alignment().max(PAGE_SIZE);
+    let slot_size = (KTLS_GUARD_SIZE + size).next_multiple_of(align);
+    let first = (KTLS_REGION.start().as_usize() + KTLS_GUARD_SIZE).next_multiple_of(align);
+
+    for (i, hart) in topology::topology().harts.iter().enumerate() {
+        let region = VirtualMemoryRegion::new(
+            VirtAddr::new_panic(first + i * slot_size),
+            MemorySize::new(size),
+        );
+        if !KTLS_REGION.contains_region(region) {
+            panic!("Kernel TLS blocks do not fit in their region");
+        }
+        let block = allocator
+            .alloc_raw(size, PAGE_SIZE, MemoryKind::KernelTls)
+            .expect("Out of memory");
+        let thread_pointer = template.init(
+            unsafe {
+                // SAFETY: Just allocated, and physical memory is identity mapped
+                slice::from_raw_parts_mut(block.as_mut_ptr::<u8>(), size)
+            },
+            region.start().as_usize(),
         );
+        let first = Page::containing(region.start());
+        for page in 0..size / PAGE_SIZE {
+            virt_mmap::map(
+                root_map,
+                allocator,
+                Frame::containing(block + page * PAGE_SIZE),
+                first + page,
+                true,
+                false,
+                MemoryType::WriteBack,
+            );
+        }
+        // Topology and TLS blocks have the same capacity
+        let _ = blocks.push(HartTls {
+            apic_id: hart.apic_id,
+            region,
+            thread_pointer: VirtAddr::new_panic(thread_pointer),
+        });
     }
-    stack.boundary() + STACK_SIZE
+    blocks
 }
 
-pub fn bsp_cede_control(kernel: &Elf<'static>, stack: VirtAddr) -> ! {
-    let entry = kernel.entry;
-    let entry = entry.as_usize();
-    let entry: extern "C" fn() -> KernelMeta = unsafe {
-        // SAFETY: sometimes rust sucks
-        mem::transmute(entry)
-    };
-    let meta = entry();
+/// Reads the [KernelMeta] note of `kernel`, and makes sure the kernel can run on this system
+pub fn kernel_meta(kernel: &Elf<'static>, features: &FeatureSet) -> KernelMeta {
+    let mut notes = kernel.notes().filter(|note| {
+        note.name == KERNEL_META_NOTE_NAME.as_bytes() && note.ty == KERNEL_META_NOTE_TYPE
+    });
+    let note = notes.next().expect("Kernel has no metadata note");
+    if notes.next().is_some() {
+        panic!("Kernel has more than one metadata note");
+    }
+    let meta = KernelMeta::from_note_desc(note.desc).expect("Kernel metadata note is corrupt");
+
+    if meta.protocol_version != BOOT_PROTOCOL_VERSION {
+        panic!(
+            "Kernel expects boot protocol version {kernel}, bootloader provides version {BOOT_PROTOCOL_VERSION}",
+            kernel = meta.protocol_version
+        );
+    }
+    let missing = meta.required_features.without(features.mask());
+    if !missing.is_empty() {
+        for feature in missing.names() {
+            error!("Kernel requires {feature}, which this CPU lacks");
+        }
+        panic!("CPU lacks features required by the kernel");
+    }
+    if meta.bsp_entry.is_none() || meta.ap_entry.is_none() {
+        panic!("Kernel metadata is missing an entry point");
+    }
+    meta
+}
 
-    let bsp_entry = meta.bsp_entry.as_usize();
+/// `bootinfo` is where [boot_protocol::BootInfo] is mapped for the kernel,
+/// `slide` is how far the kernel was mapped from where it was linked.
+/// Every hart enters the kernel on its own stack, with the FS base on its own TLS block.
+pub fn bsp_cede_control(
+    meta: &KernelMeta,
+    slide: usize,
+    stacks: &'static [HartStack],
+    tls: &'static [HartTls],
+    bootinfo: VirtAddr,
+) -> ! {
+    let (Some(bsp_entry), Some(ap_entry)) = (meta.bsp_entry, meta.ap_entry) else {
+        unreachable!("Checked in kernel_meta");
+    };
+    // The metadata is read from the file, where addresses are not relocated
+    let bsp_entry = bsp_entry as usize + slide;
 
     AP_CEDE.init(|| ApInfo {
-        ap_entry: meta.ap_entry,
-        stack_base: stack,
+        ap_entry: VirtAddr::new_panic(ap_entry as usize + slide),
+        stacks,
+        tls,
+        bootinfo,
     });
     while AP_REMAINING.load(Ordering::Relaxed) > 0 {
         hint::spin_loop();
     }
 
-    let stack = stack.as_usize();
+    let stack = own_stack(stacks).as_usize();
+    FsBase::new(own_tls(tls)).write();
 
-    do_jump(stack, bsp_entry);
+    do_jump(stack, bsp_entry, bootinfo.as_usize());
 }
 
-pub fn ap_cede_control() {
+pub fn ap_cede_control() -> ! {
     AP_REMAINING.fetch_add(1, Ordering::Relaxed);
     while AP_CEDE.get().is_none() {
         hint::spin_loop();
@@ -182,23 +351,46 @@ pub fn ap_cede_control() {
     let ap_info = AP_CEDE.get().unwrap();
 
     let ap_entry = ap_info.ap_entry.as_usize();
-    let stack = ap_info.stack_base.as_usize() + STACK_SIZE * lapic::id_cpuid();
+    let stack = own_stack(ap_info.stacks).as_usize();
+    FsBase::new(own_tls(ap_info.tls)).write();
 
     AP_REMAINING.fetch_sub(1, Ordering::Relaxed);
-    do_jump(stack, ap_entry);
+    do_jump(stack, ap_entry, ap_info.bootinfo.as_usize());
 }
 
-#[allow(unreachable_code, unused_variables)]
-fn do_jump(stack: usize, dest: usize) -> ! {
-    loop {
-        hint::spin_loop();
-    }
+fn own_stack(stacks: &[HartStack]) -> VirtAddr {
+    let apic_id = lapic::id_cpuid();
+    stacks
+        .iter()
+        .find(|stack| stack.apic_id == apic_id)
+        .expect("No kernel stack for this hart")
+        .top()
+}
+
+fn own_tls(tls: &[HartTls]) -> VirtAddr {
+    let apic_id = lapic::id_cpuid();
+    tls.iter()
+        .find(|block| block.apic_id == apic_id)
+        .expect("No kernel TLS block for this hart")
+        .thread_pointer
+}
+
+/// Number of APs which arrived in [ap_cede_control] and did not leave yet
+pub fn waiting_aps() -> usize {
+    AP_REMAINING.load(Ordering::Relaxed)
+}
+
+/// Jumps to a [boot_protocol::kernel_meta::KernelEntry], `arg` is its first sysv64 argument.
+/// `stack` is 16 byte aligned, a null return address goes on it as if `dest` had been called.
+fn do_jump(stack: usize, dest: usize, arg: usize) -> ! {
     unsafe {
         asm!(
             "mov rsp, {0}",
+            "push 0",
             "jmp {1}",
             in(reg) stack,
             in(reg) dest,
+            in("rdi") arg,
             options(noreturn)
         );
     }
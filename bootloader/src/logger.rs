@@ -17,29 +17,88 @@
 //! The last part also means that some Unicode characters might not be
 //! supported by the UEFI console. Don't expect emoji output support.
 
+use crate::allocator::ALLOCATOR_CAP;
+use crate::allocator::PostBootAllocator;
+use crate::allocator::PreBootAllocator;
+use crate::infoarea::SYSINFO_AREA;
+use crate::panic;
+use crate::panic::PanicScreen;
+use crate::timing;
+use crate::virt_mmap;
+use boot_protocol::bootlog::BOOT_LOG_SIZE;
+use boot_protocol::bootlog::BootLogWriter;
+use boot_protocol::bootlog::MAX_MESSAGE_LEN;
+use boot_protocol::ffi::RawSlice;
+use boot_protocol::framebuffer::FramebufferInfo;
+use boot_protocol::serial::SerialInfo;
+use console::font::PsfFont;
+use console::framebuffer::FramebufferConsole;
+use console::logger::ConsoleLogger;
+use console::serial::SerialLogger;
 use core::fmt;
 use core::fmt::Write;
 use core::ptr;
+use core::slice;
+use core::str;
 use core::sync::atomic::AtomicPtr;
 use core::sync::atomic::Ordering;
+use spinlocks::mutex::Mutex;
+use spinlocks::once::Once;
+use uefi::boot::MemoryType;
 use uefi::proto::console::text::Output;
 use uefi::system;
+use uefi::table;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+use x64::mem::frame::Frame;
+use x64::mem::page::Page;
+use x64::mem::paging::PagingRootEntry;
+use x64::msr::pat::MemoryType as PatMemoryType;
+use x64::serial::Uart16550;
 
 /// Global logger object
 static LOGGER: Logger = Logger::new();
 
+/// Takes over from the UEFI console once the kernel page tables are loaded
+static CONSOLE: ConsoleLogger = ConsoleLogger::new();
+
+/// Takes over from the UEFI console, which may already be redirected to it, at exit boot services
+static SERIAL: SerialLogger = SerialLogger::new();
+
+/// Every record, for the kernel to replay, see [boot_protocol::bootlog]
+static BOOT_LOG: Mutex<Option<BootLogWriter<'static>>> = Mutex::new(None);
+static BOOT_LOG_REGION: Once<PhysicalMemoryRegion> = Once::new();
+
+pub const BOOT_LOG_MEMORY: MemoryType = MemoryType::custom(0x8000_0001);
+
 /// Set up logging
 ///
 /// This is unsafe because you must arrange for the logger to be reset with
 /// disable() on exit from UEFI boot services.
+///
+/// Without a console, e.g. with no display and no serial redirection, only debugcon is used.
 pub unsafe fn init() {
     // Connect the logger to stdout.
-    system::with_stdout(|stdout| {
-        unsafe {
-            // SAFETY: Pointer made from mutable borrow
-            LOGGER.set_output(stdout)
-        };
-    });
+    if console_available() {
+        system::with_stdout(|stdout| {
+            unsafe {
+                // SAFETY: Pointer made from mutable borrow
+                LOGGER.set_output(stdout)
+            };
+        });
+    }
+
+    if let Some(memory) = PreBootAllocator.alloc_slice(BOOT_LOG_SIZE, 0u8, BOOT_LOG_MEMORY) {
+        BOOT_LOG_REGION.init(|| {
+            PhysicalMemoryRegion::new(
+                PhysAddr::new_panic(memory.as_ptr() as usize),
+                MemorySize::new(memory.len()),
+            )
+        });
+        *BOOT_LOG.lock() = BootLogWriter::new(memory);
+    }
 
     // Set the logger.
     log::set_logger(&LOGGER).unwrap(); // Can only fail if already initialized.
@@ -52,6 +111,77 @@ pub fn disable() {
     LOGGER.disable();
 }
 
+/// Logs to `framebuffer` from now on, in addition to debugcon
+///
+/// # Safety
+/// `framebuffer` must be mapped in the current page tables, and not be used by anything else
+pub unsafe fn attach_console(framebuffer: &FramebufferInfo) {
+    let console = unsafe {
+        // SAFETY: Guaranteed by the caller
+        FramebufferConsole::new(framebuffer, PsfFont::default_font())
+    };
+    // Too small for even one character
+    if let Some(console) = console {
+        CONSOLE.set_console(console);
+    }
+    panic::set_screen(unsafe {
+        // SAFETY: Guaranteed by the caller
+        PanicScreen::from_info(framebuffer)
+    });
+}
+
+/// Logs to `serial` from now on, if it answers.
+/// The UEFI console must not be used anymore, it may drive the same port.
+pub fn attach_serial(serial: &SerialInfo) {
+    let Some(port) = serial.port() else {
+        return;
+    };
+    let uart = unsafe {
+        // SAFETY: From ACPI, or the standard COM1 port
+        Uart16550::new(port)
+    };
+    if uart.init(serial.baud) {
+        SERIAL.set_uart(uart);
+        panic::set_uart(uart);
+    }
+}
+
+/// Maps the boot log read-only in the sysinfo area, the bootloader keeps appending to it
+pub fn map_boot_log(
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+) -> RawSlice<u8> {
+    let Some(region) = BOOT_LOG_REGION.get() else {
+        return RawSlice::new(&mut []);
+    };
+    let virt = SYSINFO_AREA.allocate(*region.size());
+    let frame_start = Frame::containing(region.start());
+    let page_start = Page::containing(virt);
+    for i in 0..region.size().next_multiple_of(0x1000) / 0x1000 {
+        virt_mmap::map(
+            root_map,
+            allocator,
+            frame_start + i,
+            page_start + i,
+            false,
+            false,
+            PatMemoryType::WriteBack,
+        );
+    }
+    RawSlice::new(unsafe {
+        // SAFETY: Mapped for the kernel in `root_map`
+        slice::from_raw_parts_mut(virt.as_mut_ptr(), *region.size())
+    })
+}
+
+/// Whether the firmware gave us a text console, [system::with_stdout] panics otherwise
+pub fn console_available() -> bool {
+    table::system_table_raw().is_some_and(|st| unsafe {
+        // SAFETY: Set up by the entry point, and boot services are still running
+        !st.as_ref().stdout.is_null()
+    })
+}
+
 /// Writer to the QEMU debugcon device and the debug-console of
 /// cloud-hypervisor.
 ///
@@ -61,7 +191,7 @@ pub fn disable() {
     feature = "log-debugcon"
 ))]
 #[derive(Copy, Clone, Debug)]
-struct DebugconWriter;
+pub struct DebugconWriter;
 
 #[cfg(all(
     any(target_arch = "x86", target_arch = "x86_64"),
@@ -155,6 +285,18 @@ impl log::Log for Logger {
             let _ = DecoratedLog::write(writer, record.level(), record.args());
         }
 
+        CONSOLE.log(record);
+        SERIAL.log(record);
+
+        if let Some(mut boot_log) = BOOT_LOG.try_lock() {
+            if let Some(boot_log) = boot_log.as_mut() {
+                let mut message = MessageBuffer::new();
+                let _ = write!(message, "{}", record.args());
+                let timestamp = timing::now();
+                boot_log.push(record.level(), timestamp, record.target(), message.as_str());
+            }
+        }
+
         #[cfg(all(
             any(target_arch = "x86", target_arch = "x86_64"),
             feature = "log-debugcon"
@@ -176,6 +318,39 @@ impl log::Log for Logger {
 unsafe impl Sync for Logger {}
 unsafe impl Send for Logger {}
 
+/// Formats a record for the boot log, truncating it to [MAX_MESSAGE_LEN]
+struct MessageBuffer {
+    bytes: [u8; MAX_MESSAGE_LEN],
+    len: usize,
+}
+
+impl MessageBuffer {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; MAX_MESSAGE_LEN],
+            len: 0,
+        }
+    }
+    fn as_str(&self) -> &str {
+        unsafe {
+            // SAFETY: Only whole characters are copied in `write_str`
+            str::from_utf8_unchecked(&self.bytes[..self.len])
+        }
+    }
+}
+
+impl fmt::Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(MAX_MESSAGE_LEN - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.bytes[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
 /// Writer wrapper which prints a log level in front of every line of text
 ///
 /// This is less easy than it sounds because...
@@ -4,17 +4,22 @@
 
 mod acpi;
 mod allocator;
+mod bootconfig;
 mod bootstage;
 mod entry;
 mod features;
 mod framebuffer;
 mod infoarea;
+mod kaslr;
 mod kernel;
 mod logger;
 mod misc;
+mod module;
 mod panic;
 mod phys_mmap;
 mod pic;
 mod pit;
+mod smp;
+mod timing;
 mod topology;
 mod virt_mmap;
@@ -1,10 +1,17 @@
+use crate::allocator::PreBootAllocator;
+use uefi::CStr16;
 use uefi::Error;
 use uefi::Status;
+use uefi::boot;
 use uefi::boot::MemoryType;
+use uefi::proto::device_path::DevicePathNodeEnum;
+use uefi::proto::loaded_image::LoadedImage;
 use uefi::proto::media::file::File;
+use uefi::proto::media::file::FileAttribute;
 use uefi::proto::media::file::FileInfo;
+use uefi::proto::media::file::FileMode;
 use uefi::proto::media::file::RegularFile;
-use crate::allocator::PreBootAllocator;
+use uefi::proto::media::fs::SimpleFileSystem;
 
 pub fn get_file_size(file: &mut RegularFile, allocator: &PreBootAllocator) -> Option<usize> {
     let required_size = match file.get_info::<FileInfo>(&mut []).map_err(Error::split) {
@@ -17,3 +24,53 @@ pub fn get_file_size(file: &mut RegularFile, allocator: &PreBootAllocator) -> Op
 
     Some(file_size)
 }
+
+/// Reads a whole file from the volume the bootloader was loaded from
+pub fn read_boot_file(
+    path: &CStr16,
+    mtype: MemoryType,
+    allocator: &PreBootAllocator,
+) -> Option<&'static mut [u8]> {
+    let image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let device = image.device()?;
+    let mut simple_fs = boot::open_protocol_exclusive::<SimpleFileSystem>(device).ok()?;
+    let mut volume = simple_fs.open_volume().ok()?;
+    let mut file = volume
+        .open(path, FileMode::Read, FileAttribute::empty())
+        .ok()?
+        .into_regular_file()?;
+
+    let file_size = get_file_size(&mut file, allocator)?;
+    let buffer = allocator.alloc_slice(file_size, 0u8, mtype)?;
+    let read = file.read(buffer).ok()?;
+    Some(&mut buffer[..read])
+}
+
+/// Builds the path of `filename` in the directory the bootloader image is in
+pub fn image_sibling<'a>(filename: &str, buffer: &'a mut [u16]) -> Option<&'a CStr16> {
+    let image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let image_path = image
+        .file_path()?
+        .node_iter()
+        .find_map(|node| match node.as_enum() {
+            Ok(DevicePathNodeEnum::MediaFilePath(path)) => Some(path.path_name()),
+            _ => None,
+        })?;
+
+    let mut len = 0;
+    let mut directory_len = 0;
+    for c in image_path.iter().take_while(|&c| c != 0) {
+        *buffer.get_mut(len)? = c;
+        len += 1;
+        if c == u16::from(b'\\') {
+            directory_len = len;
+        }
+    }
+    len = directory_len;
+    for c in filename.encode_utf16() {
+        *buffer.get_mut(len)? = c;
+        len += 1;
+    }
+    *buffer.get_mut(len)? = 0;
+    CStr16::from_u16_with_nul(&buffer[..=len]).ok()
+}
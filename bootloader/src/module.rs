@@ -0,0 +1,101 @@
+use crate::allocator::ALLOCATOR_CAP;
+use crate::allocator::PostBootAllocator;
+use crate::allocator::PreBootAllocator;
+use crate::bootconfig::BootConfig;
+use crate::infoarea::SYSINFO_AREA;
+use crate::misc;
+use crate::virt_mmap;
+use boot_protocol::module::MAX_MODULE_COUNT;
+use boot_protocol::module::MAX_MODULE_NAME_LEN;
+use boot_protocol::module::Module;
+use common::collections::smallvec::SmallVec;
+use log::info;
+use uefi::CStr16;
+use uefi::boot::MemoryType;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+use x64::mem::frame::Frame;
+use x64::mem::page::Page;
+use x64::mem::paging::PagingRootEntry;
+use x64::msr::pat::MemoryType as PatMemoryType;
+
+/// Module contents get their own memory type, so that they can be told apart from bootloader data
+pub const MODULE_MEMORY: MemoryType = MemoryType::custom(0x8000_0000);
+
+pub struct LoadedModule {
+    name: &'static str,
+    data: &'static [u8],
+}
+
+pub fn load_modules(
+    config: &BootConfig,
+    allocator: &PreBootAllocator,
+) -> SmallVec<LoadedModule, MAX_MODULE_COUNT> {
+    let mut modules = SmallVec::new();
+    for module in &config.modules {
+        if module.name.len() > MAX_MODULE_NAME_LEN {
+            panic!(
+                "Module name \"{name}\" too long (maximum supported: {MAX_MODULE_NAME_LEN})",
+                name = module.name
+            );
+        }
+        let mut path_buf = [0u16; 256];
+        let path =
+            CStr16::from_str_with_buf(module.path, &mut path_buf).expect("Filename too long");
+        let Some(data) = misc::read_boot_file(path, MODULE_MEMORY, allocator) else {
+            panic!("Failed to read module file {path}", path = module.path);
+        };
+        info!(
+            "Loaded module {name} from {path} ({size} bytes)",
+            name = module.name,
+            path = module.path,
+            size = data.len()
+        );
+        let loaded = LoadedModule {
+            name: module.name,
+            data,
+        };
+        if modules.push(loaded).is_err() {
+            unreachable!("Same capacity as the configuration");
+        }
+    }
+    modules
+}
+
+/// Maps every module read-only in the sysinfo area
+pub fn map_modules(
+    modules: &[LoadedModule],
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+) -> SmallVec<Module, MAX_MODULE_COUNT> {
+    let mut mapped = SmallVec::new();
+    for module in modules {
+        let size = MemorySize::new(module.data.len());
+        let phys =
+            PhysicalMemoryRegion::new(PhysAddr::new_panic(module.data.as_ptr() as usize), size);
+        let virt = VirtualMemoryRegion::new(SYSINFO_AREA.allocate(*size), size);
+
+        let frame_start = Frame::containing(phys.start());
+        let page_start = Page::containing(virt.start());
+        for i in 0..size.next_multiple_of(0x1000) / 0x1000 {
+            virt_mmap::map(
+                root_map,
+                allocator,
+                frame_start + i,
+                page_start + i,
+                false,
+                false,
+                PatMemoryType::WriteBack,
+            );
+        }
+
+        let module = Module::new(module.name, phys, virt).expect("Checked in load_modules");
+        if mapped.push(module).is_err() {
+            unreachable!("Same capacity as the loaded modules");
+        }
+    }
+    mapped
+}
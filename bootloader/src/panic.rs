@@ -1,11 +1,51 @@
+//! Once boot services are gone, nothing the bootloader logs shows up anywhere until the
+//! kernel replays it, so post-boot panics are drawn straight to the framebuffer and serial port.
+
+use crate::bootstage;
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    feature = "log-debugcon"
+))]
+use crate::logger::DebugconWriter;
+use crate::timing;
+use boot_protocol::framebuffer::FramebufferInfo;
+use common::debug::write_memory_dump;
+use console::font::PsfFont;
+use console::framebuffer::FramebufferConsole;
 use core::arch::asm;
+use core::fmt;
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::slice;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
 use log::error;
+use spinlocks::mutex::Mutex;
+use x64::framebuffer::PixelColor;
+use x64::framebuffer::PixelMode;
+use x64::serial::Uart16550;
+
+/// Framebuffer to draw the panic screen to, valid in the page tables currently loaded
+static SCREEN: Mutex<Option<PanicScreen>> = Mutex::new(None);
+static UART: Mutex<Option<Uart16550>> = Mutex::new(None);
+/// Set by the first post-boot panic, a panic while drawing it just halts
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Bytes of stack shown from the stack pointer up
+const STACK_DUMP_SIZE: usize = 128;
+
+const TEXT_COLOR: PixelColor = PixelColor(0xFF, 0xFF, 0xFF);
+const BACKGROUND_COLOR: PixelColor = PixelColor(0x80, 0, 0);
 
 #[cfg(not(any(test, doc)))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-    if let Some(location) = info.location() {
+    let registers = Registers::capture();
+    if bootstage::is_postboot() {
+        if !PANICKING.swap(true, Ordering::Relaxed) {
+            panic_screen(info, &registers);
+        }
+    } else if let Some(location) = info.location() {
         error!(
             "Bootloader Panic({location}): {message}",
             message = info.message()
@@ -14,6 +54,206 @@ fn panic(info: &PanicInfo) -> ! {
         error!("Bootloader Panic: {message}", message = info.message());
     }
     loop {
-        unsafe { asm!("hlt") }
+        unsafe { asm!("cli", "hlt") }
+    }
+}
+
+/// Where to draw the panic screen, see [set_screen]
+#[derive(Clone, Copy)]
+pub struct PanicScreen {
+    fb: *mut u8,
+    len: usize,
+    width: usize,
+    height: usize,
+    /// In pixels
+    stride: usize,
+    mode: PixelMode,
+}
+
+// Only ever used by the panicking hart
+unsafe impl Send for PanicScreen {}
+
+impl PanicScreen {
+    /// # Safety
+    /// `fb` must point to the `len` bytes of a framebuffer, mapped in the page tables loaded
+    /// for as long as it is given to [set_screen]
+    pub const unsafe fn new(
+        fb: *mut u8,
+        len: usize,
+        size: (usize, usize),
+        stride: usize,
+        mode: PixelMode,
+    ) -> Self {
+        Self {
+            fb,
+            len,
+            width: size.0,
+            height: size.1,
+            stride,
+            mode,
+        }
+    }
+
+    /// # Safety
+    /// See [PanicScreen::new], `info`'s framebuffer must be mapped
+    pub unsafe fn from_info(info: &FramebufferInfo) -> Self {
+        unsafe {
+            // SAFETY: Guaranteed by the caller
+            Self::new(
+                info.fb.as_ptr(),
+                info.fb.len(),
+                (info.width, info.height),
+                info.stride,
+                info.mode,
+            )
+        }
+    }
+}
+
+/// Draw post-boot panics to `screen` from now on
+pub fn set_screen(screen: PanicScreen) {
+    *SCREEN.lock() = Some(screen);
+}
+
+/// Stop drawing panics, e.g. before loading page tables the screen is not mapped in
+pub fn clear_screen() {
+    *SCREEN.lock() = None;
+}
+
+/// Write post-boot panics to `uart` from now on, it must already be initialized
+pub fn set_uart(uart: Uart16550) {
+    *UART.lock() = Some(uart);
+}
+
+fn panic_screen(info: &PanicInfo, registers: &Registers) {
+    let mut out = PanicOutput::take();
+    let _ = write_panic(&mut out, info, registers);
+}
+
+fn write_panic(out: &mut PanicOutput, info: &PanicInfo, registers: &Registers) -> fmt::Result {
+    writeln!(out, "Bootloader panic: {}", info.message())?;
+    if let Some(location) = info.location() {
+        writeln!(out, "  at {location}")?;
+    }
+    match timing::current() {
+        Some(phase) => writeln!(out, "  during {} (post-boot)", phase.name())?,
+        None => writeln!(out, "  post-boot")?,
+    }
+    writeln!(out)?;
+    writeln!(out, "{registers}")?;
+    writeln!(out, "Stack:")?;
+    write_memory_dump(out, registers.rsp as usize, STACK_DUMP_SIZE)
+}
+
+/// Everything a post-boot panic is written to
+struct PanicOutput {
+    console: Option<FramebufferConsole>,
+    uart: Option<Uart16550>,
+}
+
+impl PanicOutput {
+    /// Whoever held the locks is not running anymore, we are panicking on the only hart
+    /// the bootloader runs on
+    fn take() -> Self {
+        let console = SCREEN
+            .try_lock()
+            .and_then(|screen| *screen)
+            .and_then(|screen| {
+                let fb = unsafe {
+                    // SAFETY: Guaranteed when the screen was set
+                    slice::from_raw_parts_mut(screen.fb, screen.len)
+                };
+                let mut console = FramebufferConsole::from_parts(
+                    fb,
+                    None,
+                    (screen.width, screen.height),
+                    screen.stride,
+                    screen.mode,
+                    PsfFont::default_font(),
+                )?;
+                console.set_colors(TEXT_COLOR, BACKGROUND_COLOR);
+                console.clear();
+                Some(console)
+            });
+        let uart = UART.try_lock().and_then(|uart| *uart);
+        Self { console, uart }
+    }
+}
+
+impl fmt::Write for PanicOutput {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(console) = &mut self.console {
+            let _ = console.write_str(s);
+        }
+        if let Some(uart) = &mut self.uart {
+            let _ = uart.write_str(s);
+        }
+        #[cfg(all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            feature = "log-debugcon"
+        ))]
+        {
+            let _ = DebugconWriter.write_str(s);
+        }
+        Ok(())
+    }
+}
+
+/// What the panicking hart looked like, as close to the panic as we can get
+struct Registers {
+    rsp: u64,
+    rbp: u64,
+    rflags: u64,
+    cr0: u64,
+    cr2: u64,
+    cr3: u64,
+    cr4: u64,
+}
+
+impl Registers {
+    #[inline(always)]
+    fn capture() -> Self {
+        let (rsp, rbp, rflags, cr0, cr2, cr3, cr4);
+        unsafe {
+            // SAFETY: Only reads registers
+            asm!(
+                "mov {rsp}, rsp",
+                "mov {rbp}, rbp",
+                "pushfq",
+                "pop {rflags}",
+                "mov {cr0}, cr0",
+                "mov {cr2}, cr2",
+                "mov {cr3}, cr3",
+                "mov {cr4}, cr4",
+                rsp = out(reg) rsp,
+                rbp = out(reg) rbp,
+                rflags = out(reg) rflags,
+                cr0 = out(reg) cr0,
+                cr2 = out(reg) cr2,
+                cr3 = out(reg) cr3,
+                cr4 = out(reg) cr4,
+            );
+        }
+        Self {
+            rsp,
+            rbp,
+            rflags,
+            cr0,
+            cr2,
+            cr3,
+            cr4,
+        }
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "RSP {:016x} RBP {:016x} RFLAGS {:016x}",
+            self.rsp, self.rbp, self.rflags
+        )?;
+        writeln!(f, "CR0 {:016x} CR2 {:016x}", self.cr0, self.cr2)?;
+        write!(f, "CR3 {:016x} CR4 {:016x}", self.cr3, self.cr4)
     }
 }
@@ -1,8 +1,20 @@
+use crate::logger::BOOT_LOG_MEMORY;
+use crate::module::MODULE_MEMORY;
+use boot_protocol::mmap::MemoryKind;
+use boot_protocol::mmap::MemoryMap;
+use boot_protocol::mmap::MemoryMapEntry;
+use config::pmem::SUPERLOWMEM;
 use core::cmp::Ordering;
 use core::ops::Deref;
 use core::ops::DerefMut;
 use core::slice;
+use uefi::boot::MemoryType;
+use uefi::mem::memory_map::MemoryMap as UefiMemoryMap;
+use uefi::mem::memory_map::MemoryMapOwned;
+use x64::mem::MemorySize;
 use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
 
 pub struct PhysMemMap<const MAX: usize> {
     pub regions: [PhysicalMemoryRegion; MAX],
@@ -35,20 +47,23 @@ impl<const MAX: usize> PhysMemMap<MAX> {
 }
 
 impl<const MAX: usize> PhysMemMap<MAX> {
-    pub fn add(&mut self, region: PhysicalMemoryRegion) {
+    /// Gives `region` back if it can't be merged and the map is full
+    pub fn add(&mut self, region: PhysicalMemoryRegion) -> Result<(), PhysicalMemoryRegion> {
         // Merge with entry if overlapping or if tail of one is head of other
         for entry in &mut self.regions[..self.len] {
             if let Some(combined) = *entry + region {
                 *entry = combined;
                 self.minimize();
-                return;
+                return Ok(());
             }
         }
-        if self.len < MAX {
-            self.regions[self.len] = region;
-            self.len += 1;
-            self.minimize();
+        if self.len == MAX {
+            return Err(region);
         }
+        self.regions[self.len] = region;
+        self.len += 1;
+        self.minimize();
+        Ok(())
     }
     pub fn minimize(&mut self) {
         self.sort_start_addr();
@@ -115,3 +130,65 @@ impl<'a, const MAX: usize> IntoIterator for &'a PhysMemMap<MAX> {
         self.iter()
     }
 }
+
+/// Builds the memory map handed to the kernel out of the UEFI memory map, and of what
+/// the post boot allocator did with conventional memory above 1M.
+/// Gives back the first entry that did not fit.
+pub fn typed_memory_map<const MAX: usize>(
+    mmap: &mut MemoryMap,
+    uefi_mmap: &MemoryMapOwned,
+    free: &PhysMemMap<MAX>,
+    used: &MemoryMap,
+    framebuffers: impl Iterator<Item = PhysicalMemoryRegion>,
+    trampoline: PhysicalMemoryRegion,
+) -> Result<(), MemoryMapEntry> {
+    for region in free {
+        mmap.push(MemoryMapEntry::new(*region, MemoryKind::Usable))?;
+    }
+    for entry in used {
+        mmap.push(*entry)?;
+    }
+    for entry in uefi_mmap.entries() {
+        let region = PhysicalMemoryRegion::new(
+            PhysAddr::new_panic(entry.phys_start as usize),
+            MemorySize::new(entry.page_count as usize * 4096),
+        );
+        let lowmem = SUPERLOWMEM.contains(region.start());
+        let kind = match entry.ty {
+            // The kernel is entered with the firmware's GDT and IDT, which live in there
+            MemoryType::BOOT_SERVICES_CODE | MemoryType::BOOT_SERVICES_DATA => {
+                MemoryKind::BootloaderReclaimable
+            }
+            MemoryType::CONVENTIONAL | MemoryType::LOADER_CODE | MemoryType::LOADER_DATA
+                if lowmem =>
+            {
+                MemoryKind::SuperLowMemory
+            }
+            // Already covered by the allocator
+            MemoryType::CONVENTIONAL => continue,
+            MemoryType::LOADER_CODE | MemoryType::LOADER_DATA => MemoryKind::BootloaderReclaimable,
+            MODULE_MEMORY => MemoryKind::Module,
+            BOOT_LOG_MEMORY => MemoryKind::BootInfo,
+            MemoryType::ACPI_RECLAIM => MemoryKind::AcpiReclaimable,
+            MemoryType::ACPI_NON_VOLATILE => MemoryKind::AcpiNvs,
+            MemoryType::RUNTIME_SERVICES_CODE => MemoryKind::RuntimeServicesCode,
+            MemoryType::RUNTIME_SERVICES_DATA => MemoryKind::RuntimeServicesData,
+            MemoryType::MMIO | MemoryType::MMIO_PORT_SPACE => MemoryKind::Mmio,
+            MemoryType::PERSISTENT_MEMORY => MemoryKind::PersistentMemory,
+            MemoryType::UNUSABLE => MemoryKind::Unusable,
+            _ => MemoryKind::Reserved,
+        };
+        mmap.push(MemoryMapEntry::new(region, kind))?;
+    }
+    // Framebuffers may or may not be reported by the firmware, and as anything
+    for framebuffer in framebuffers {
+        mmap.overlay(MemoryMapEntry::new(framebuffer, MemoryKind::Framebuffer))?;
+    }
+    // APs keep their GDTR pointing in it until the kernel gives them a GDT
+    mmap.overlay(MemoryMapEntry::new(
+        trampoline,
+        MemoryKind::BootloaderReclaimable,
+    ))?;
+    mmap.normalize();
+    Ok(())
+}
@@ -5,10 +5,17 @@ use x64::io::Port;
 const CH0_DATA: Port<u8> = Port::new(0x40);
 const CMD: Port<u8> = Port::new(0x43);
 
-const BASE_FREQ: usize = 1193182;
+/// Ticks per second
+pub const BASE_FREQ: usize = 1193182;
 
 /// Sleep with a precision of 5us and accuracy > 99% (not counting hardware accuracy) for AT LEAST t us
 pub fn sleep_us(t: usize) {
+    sleep_us_measured(t);
+}
+
+/// Same as [sleep_us], returns how many ticks of [BASE_FREQ] actually elapsed.
+/// `t` must be under 50ms, the counter only holds 16 bits.
+pub fn sleep_us_measured(t: usize) -> usize {
     const TU_PER_5US: usize = 6;
     let us5_amount = (t / 5) + 1;
     let delta_units = us5_amount * TU_PER_5US;
@@ -18,6 +25,9 @@ pub fn sleep_us(t: usize) {
     unsafe {
         // # Safety
         // No side effect
+        // Channel 0, lo/hi byte, mode 2. The firmware may have left it in mode 3, which counts
+        // down by 2 per tick
+        CMD.write(0x34);
         CH0_DATA.write(0xFF);
         CH0_DATA.write(0xFF);
     }
@@ -32,7 +42,7 @@ pub fn sleep_us(t: usize) {
         } as usize;
 
         if current_time < expected_delivery_time {
-            break;
+            return 0xFFFF - current_time;
         }
         hint::spin_loop();
     }
@@ -0,0 +1,347 @@
+use crate::allocator::ALLOCATOR_CAP;
+use crate::allocator::LOWMEM_ALLOCATOR;
+use crate::allocator::PostBootAllocator;
+use crate::kernel;
+use crate::pit;
+use crate::topology;
+use crate::virt_mmap;
+use boot_protocol::LOCAL_APIC_MAPPING;
+use boot_protocol::mmap::MemoryKind;
+use core::arch::global_asm;
+use core::mem;
+use core::ptr;
+use log::warn;
+use x64::lapic;
+use x64::lapic::IPIDeliveryMode;
+use x64::lapic::IPIDestination;
+use x64::lapic::IPIDestinationMode;
+use x64::lapic::IPILevel;
+use x64::lapic::InterProcessorInterrupt;
+use x64::lapic::LocalApicPointer;
+use x64::mem::MemorySize;
+use x64::mem::PhysicalMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::PhysAddr;
+use x64::mem::addr::VirtAddr;
+use x64::mem::frame::Frame;
+use x64::mem::frame::size::Frame64KiB;
+use x64::mem::frame::size::FrameSize;
+use x64::mem::page::Page;
+use x64::mem::page::size::Page1GiB;
+use x64::mem::page::size::Page2MiB;
+use x64::mem::page::size::Page512GiB;
+use x64::mem::paging::PagingMapEntry;
+use x64::mem::paging::PagingRawEntry;
+use x64::mem::paging::PagingReferenceEntry;
+use x64::mem::paging::PagingRootEntry;
+use x64::msr::apic_base::ApicBase;
+use x64::msr::efer::Efer;
+use x64::msr::pat::MemoryType;
+use x64::msr::pat::standard_pat;
+
+// Layout of the 64K trampoline chunk, relative to its start.
+// The code goes at offset 0, since that is where the SIPI vector points to.
+const DATA_OFFSET: usize = 0xF00;
+const PML4_OFFSET: usize = 0x1000;
+const PDPT_OFFSET: usize = 0x2000;
+const PD_OFFSET: usize = 0x3000;
+
+const CODE32_SELECTOR: u16 = 0x08;
+const DATA_SELECTOR: u16 = 0x10;
+const CODE64_SELECTOR: u16 = 0x18;
+
+/// Stack used by each AP from the trampoline until it gets its kernel stack
+const AP_BOOT_STACK_SIZE: usize = 16 * 1024;
+
+/// Filled by the BSP, read by the trampoline
+#[repr(C, packed)]
+struct TrampolineData {
+    gdt: [u64; 4],
+    gdtr: DescriptorPointer32,
+    pm_entry: FarPointer32,
+    lm_entry: FarPointer32,
+    temp_cr3: u32,
+    cr3: u64,
+    stack: u64,
+    entry: u64,
+}
+
+#[repr(C, packed)]
+struct DescriptorPointer32 {
+    limit: u16,
+    base: u32,
+}
+
+#[repr(C, packed)]
+struct FarPointer32 {
+    offset: u32,
+    selector: u16,
+}
+
+pub struct Trampoline {
+    base: PhysAddr,
+}
+
+impl Trampoline {
+    /// The whole super low memory chunk, code and data
+    pub fn region(&self) -> PhysicalMemoryRegion {
+        PhysicalMemoryRegion::new(self.base, MemorySize::new(Frame64KiB::SIZE))
+    }
+}
+
+// Real mode -> protected mode -> long mode, loads the bootloader page tables
+// then calls `entry` on `stack`. ESI keeps the trampoline base all along.
+global_asm!(
+    ".global ap_trampoline_start",
+    "ap_trampoline_start:",
+    ".code16",
+    "cli",
+    "cld",
+    "mov ax, cs",
+    "mov ds, ax",
+    "xor esi, esi",
+    "mov si, ax",
+    "shl esi, 4",
+    "lgdt [{gdtr}]",
+    "mov eax, cr0",
+    "or eax, 1",
+    "mov cr0, eax",
+    "jmp fword ptr [{pm_entry}]",
+    ".global ap_trampoline_pm",
+    "ap_trampoline_pm:",
+    ".code32",
+    "mov ax, {data_selector}",
+    "mov ds, ax",
+    "mov es, ax",
+    "mov ss, ax",
+    // PAE
+    "mov eax, cr4",
+    "or eax, 1 << 5",
+    "mov cr4, eax",
+    "mov eax, [esi + {temp_cr3}]",
+    "mov cr3, eax",
+    // EFER.LME and EFER.NXE, the bootloader page tables use the NX bit
+    "mov ecx, 0xC0000080",
+    "rdmsr",
+    "or eax, (1 << 8) | (1 << 11)",
+    "wrmsr",
+    // Paging
+    "mov eax, cr0",
+    "or eax, 1 << 31",
+    "mov cr0, eax",
+    "jmp fword ptr [esi + {lm_entry}]",
+    ".global ap_trampoline_lm",
+    "ap_trampoline_lm:",
+    ".code64",
+    "mov esi, esi",
+    "mov rax, [rsi + {cr3}]",
+    "mov cr3, rax",
+    "mov rsp, [rsi + {stack}]",
+    "mov rax, [rsi + {entry}]",
+    "call rax",
+    "ud2",
+    ".global ap_trampoline_end",
+    "ap_trampoline_end:",
+    gdtr = const DATA_OFFSET + mem::offset_of!(TrampolineData, gdtr),
+    pm_entry = const DATA_OFFSET + mem::offset_of!(TrampolineData, pm_entry),
+    lm_entry = const DATA_OFFSET + mem::offset_of!(TrampolineData, lm_entry),
+    temp_cr3 = const DATA_OFFSET + mem::offset_of!(TrampolineData, temp_cr3),
+    cr3 = const DATA_OFFSET + mem::offset_of!(TrampolineData, cr3),
+    stack = const DATA_OFFSET + mem::offset_of!(TrampolineData, stack),
+    entry = const DATA_OFFSET + mem::offset_of!(TrampolineData, entry),
+    data_selector = const DATA_SELECTOR,
+);
+
+unsafe extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_pm: u8;
+    static ap_trampoline_lm: u8;
+    static ap_trampoline_end: u8;
+}
+
+/// Copies the trampoline to super low memory, and maps it in `root_map`, along with the local APIC
+/// at [LOCAL_APIC_MAPPING].
+/// Must be called before `root_map` is loaded, while super low memory is still identity mapped.
+pub fn prepare(
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+) -> Trampoline {
+    let chunk = LOWMEM_ALLOCATOR
+        .alloc64k()
+        .expect("No super low memory left for AP trampoline");
+    let base = chunk.boundary();
+
+    let code_start = &raw const ap_trampoline_start as usize;
+    let code_end = &raw const ap_trampoline_end as usize;
+    let code_size = code_end - code_start;
+    if code_size > DATA_OFFSET {
+        panic!("AP trampoline code too big");
+    }
+    unsafe {
+        // SAFETY: The chunk is ours, and is identity mapped until `root_map` is loaded
+        ptr::write_bytes(base.as_mut_ptr::<u8>(), 0, Frame64KiB::SIZE);
+        ptr::copy_nonoverlapping(code_start as *const u8, base.as_mut_ptr(), code_size);
+    }
+
+    let pml4 = base + PML4_OFFSET;
+    let pdpt = base + PDPT_OFFSET;
+    let pd = base + PD_OFFSET;
+    unsafe {
+        // SAFETY: Same as above, the tables are inside the zeroed chunk
+        pml4.to_mut::<[PagingRawEntry<Page512GiB>; 512]>()[0] =
+            PagingReferenceEntry::<Page512GiB>::new(Frame::containing(pdpt))
+                .write()
+                .exec()
+                .to_raw();
+        pdpt.to_mut::<[PagingRawEntry<Page1GiB>; 512]>()[0] =
+            PagingReferenceEntry::<Page1GiB>::new(Frame::containing(pd))
+                .write()
+                .exec()
+                .to_raw();
+        // Identity maps the first 2M, which contains the trampoline
+        pd.to_mut::<[PagingRawEntry<Page2MiB>; 512]>()[0] =
+            PagingMapEntry::<Page2MiB>::new(Frame::containing(PhysAddr::null()))
+                .write()
+                .exec()
+                .to_raw();
+    }
+
+    let symbol_offset = |symbol: usize| (base.as_usize() + symbol - code_start) as u32;
+    let data = TrampolineData {
+        gdt: [
+            0,
+            0x00CF_9A00_0000_FFFF, // 32 bit code
+            0x00CF_9200_0000_FFFF, // data
+            0x00AF_9A00_0000_FFFF, // 64 bit code
+        ],
+        gdtr: DescriptorPointer32 {
+            limit: (mem::size_of::<[u64; 4]>() - 1) as u16,
+            base: (base.as_usize() + DATA_OFFSET + mem::offset_of!(TrampolineData, gdt)) as u32,
+        },
+        pm_entry: FarPointer32 {
+            offset: symbol_offset(&raw const ap_trampoline_pm as usize),
+            selector: CODE32_SELECTOR,
+        },
+        lm_entry: FarPointer32 {
+            offset: symbol_offset(&raw const ap_trampoline_lm as usize),
+            selector: CODE64_SELECTOR,
+        },
+        temp_cr3: pml4.as_usize() as u32,
+        cr3: *root_map,
+        stack: 0,
+        entry: ap_main as usize as u64,
+    };
+    unsafe {
+        // SAFETY: Same as above
+        (base + DATA_OFFSET)
+            .as_mut_ptr::<TrampolineData>()
+            .write(data);
+    }
+
+    let chunk_start = Frame::containing(base);
+    let chunk_page_start = Page::containing(base.to_virt());
+    for i in 0..Frame64KiB::SIZE / 4096 {
+        virt_mmap::map(
+            root_map,
+            allocator,
+            chunk_start + i,
+            chunk_page_start + i,
+            true,
+            true,
+            MemoryType::WriteBack,
+        );
+    }
+
+    let lapic_base = ApicBase::get().base();
+    virt_mmap::map(
+        root_map,
+        allocator,
+        Frame::containing(lapic_base),
+        Page::containing(VirtAddr::new_panic(LOCAL_APIC_MAPPING)),
+        true,
+        false,
+        MemoryType::Uncacheable,
+    );
+
+    Trampoline { base }
+}
+
+/// Sends INIT-SIPI-SIPI to every registered hart but the BSP, one at a time,
+/// and waits for each of them to arrive in [kernel::ap_cede_control].
+/// Stops at the first hart that does not arrive in time, it could still come out of the
+/// trampoline later, on the boot stack of the next one.
+/// `root_map` from [prepare] must be loaded.
+pub fn start_aps(trampoline: &Trampoline, allocator: &mut PostBootAllocator<ALLOCATOR_CAP>) {
+    let lapic = LocalApicPointer::new(VirtAddr::new_panic(LOCAL_APIC_MAPPING));
+    let bsp_id = lapic::id_cpuid();
+    let vector = (trampoline.base.as_usize() >> 12) as u8;
+    let data = (trampoline.base + DATA_OFFSET).as_mut_ptr::<TrampolineData>();
+
+    let topology = topology::topology();
+    for hart in topology.harts.iter().filter(|hart| hart.apic_id != bsp_id) {
+        // xAPIC IPIs only address 8 bit APIC IDs
+        let Ok(apic_id) = u8::try_from(hart.apic_id) else {
+            warn!(
+                "Hart with x2APIC ID {} can not be addressed, leaving it alone",
+                hart.apic_id
+            );
+            continue;
+        };
+        let stack = allocator
+            .alloc_raw(
+                AP_BOOT_STACK_SIZE,
+                0x1000,
+                MemoryKind::BootloaderReclaimable,
+            )
+            .expect("Out of memory");
+        unsafe {
+            // SAFETY: The previous AP already left the trampoline, or we would have stopped
+            (*data).stack = (stack + AP_BOOT_STACK_SIZE).as_u64();
+        }
+
+        let arrived = kernel::waiting_aps();
+        let target = IPIDestination::Explicit {
+            tartget_apicid: apic_id,
+        };
+        lapic.send_ipi(InterProcessorInterrupt {
+            delivery_mode: IPIDeliveryMode::Init {
+                level: IPILevel::Assert,
+            },
+            destination_mode: IPIDestinationMode::Physical,
+            destination: target,
+        });
+        pit::sleep_us(10_000);
+
+        let sipi = InterProcessorInterrupt {
+            delivery_mode: IPIDeliveryMode::StartUp { vector },
+            destination_mode: IPIDestinationMode::Physical,
+            destination: target,
+        };
+        lapic.send_ipi(sipi);
+        pit::sleep_us(200);
+        if kernel::waiting_aps() == arrived {
+            lapic.send_ipi(sipi);
+        }
+
+        // Give it up to 100ms
+        for _ in 0..1000 {
+            if kernel::waiting_aps() != arrived {
+                break;
+            }
+            pit::sleep_us(100);
+        }
+        if kernel::waiting_aps() == arrived {
+            warn!(
+                "Hart with APIC ID {} did not start, leaving it and the remaining ones alone",
+                apic_id
+            );
+            break;
+        }
+    }
+}
+
+extern "sysv64" fn ap_main() -> ! {
+    Efer::new().syscall(false).exec_disable(true).write();
+    standard_pat().write();
+    kernel::ap_cede_control();
+}
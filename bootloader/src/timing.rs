@@ -0,0 +1,80 @@
+//! Boot phase timestamps for [boot_protocol::timing]
+
+use crate::pit;
+use boot_protocol::timing::BootPhase;
+use boot_protocol::timing::BootTimeline;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+use log::debug;
+use spinlocks::mutex::Mutex;
+use x64::tsc;
+
+static TIMELINE: Mutex<BootTimeline> = Mutex::new(BootTimeline::new(0));
+/// Phase begun and not ended yet, for the panic screen
+static CURRENT: Mutex<Option<BootPhase>> = Mutex::new(None);
+/// Kept out of [TIMELINE] so the logger can timestamp records without locking
+static START_TSC: AtomicU64 = AtomicU64::new(0);
+
+/// How long the TSC is measured against the PIT when CPUID does not tell its frequency
+const CALIBRATION_US: u64 = 10_000;
+
+/// Marks the start of the boot, call before anything else
+pub fn init() {
+    START_TSC.store(tsc::read(), Ordering::Relaxed);
+}
+
+/// TSC ticks since [init]
+pub fn now() -> u64 {
+    tsc::read().saturating_sub(START_TSC.load(Ordering::Relaxed))
+}
+
+/// Call [Phase::end] once `phase` is done
+pub fn begin(phase: BootPhase) -> Phase {
+    *CURRENT.lock() = Some(phase);
+    Phase {
+        phase,
+        start: now(),
+    }
+}
+
+/// Phase the bootloader is in, if any, `None` if unknown
+pub fn current() -> Option<BootPhase> {
+    CURRENT.try_lock().and_then(|current| *current)
+}
+
+/// Measures the TSC frequency, falling back to the PIT when CPUID does not enumerate it.
+///
+/// Only call this after exiting boot services, the PIT belongs to the firmware before.
+pub fn calibrate() {
+    let frequency = tsc::cpuid_frequency().unwrap_or_else(|| {
+        let start = tsc::read();
+        // The sleep overshoots, so scale by the PIT ticks it lasted rather than its length
+        let ticks = pit::sleep_us_measured(CALIBRATION_US as usize) as u64;
+        (tsc::read() - start) * pit::BASE_FREQ as u64 / ticks
+    });
+    debug!("TSC frequency {} kHz", frequency / 1000);
+    TIMELINE.lock().tsc_frequency = frequency;
+}
+
+/// Records the start of [BootPhase::CedeControl], and returns the finished timeline
+pub fn cede_control() -> BootTimeline {
+    let start = now();
+    let mut timeline = TIMELINE.lock();
+    timeline.start_tsc = START_TSC.load(Ordering::Relaxed);
+    timeline.record(BootPhase::CedeControl, start, start);
+    timeline.clone()
+}
+
+#[must_use = "Call Phase::end once the phase is done"]
+pub struct Phase {
+    phase: BootPhase,
+    start: u64,
+}
+
+impl Phase {
+    pub fn end(self) {
+        let end = now();
+        *CURRENT.lock() = None;
+        TIMELINE.lock().record(self.phase, self.start, end);
+    }
+}
@@ -37,9 +37,10 @@ pub fn dump() {
     );
     for hart in &topology.harts {
         debug!(
-            "\t\tHart#{apic}@{acpi}",
+            "\t\tHart#{apic}@{acpi}{bsp}",
             apic = hart.apic_id,
-            acpi = hart.acpi_id
+            acpi = hart.acpi_id,
+            bsp = if hart.bsp { " (BSP)" } else { "" }
         );
     }
     debug!(
@@ -1,16 +1,25 @@
 use crate::allocator::ALLOCATOR_CAP;
 use crate::allocator::PostBootAllocator;
+use crate::logger::BOOT_LOG_MEMORY;
+use acpi::table::Rsdp;
 use boot_protocol::BootInfo;
+use boot_protocol::acpi::AcpiInfo;
+use boot_protocol::mmap::MemoryKind;
+use config::vmem::PHYSICAL_MAPPING_REGION;
 use core::mem;
 use uefi::boot::MemoryType;
+use uefi::mem::memory_map::MemoryAttribute;
+use uefi::mem::memory_map::MemoryDescriptor;
 use uefi::mem::memory_map::MemoryMap;
 use uefi::mem::memory_map::MemoryMapOwned;
 use x64::mem::MemorySize;
 use x64::mem::PhysicalMemoryRegion;
 use x64::mem::addr::Address;
 use x64::mem::addr::PhysAddr;
+use x64::mem::addr::VirtAddr;
 use x64::mem::frame::Frame;
 use x64::mem::frame::size::Frame4KiB;
+use x64::mem::frame::size::FrameSize;
 use x64::mem::page::Page;
 use x64::mem::page::size::Page1GiB;
 use x64::mem::page::size::Page2MiB;
@@ -37,12 +46,20 @@ pub fn map(
 ) {
     let pml4_table = unsafe { root.target_mut() };
 
-    let pdp_table = target_or_alloc(
+    let Some(pdp_table) = target_or_alloc(
         pml4_table[page.order_index::<Page512GiB>()].as_raw(),
         allocator,
-    );
-    let pd_table = target_or_alloc(&mut pdp_table[page.order_index::<Page1GiB>()], allocator);
-    let pt_table = target_or_alloc(&mut pd_table[page.order_index::<Page2MiB>()], allocator);
+    ) else {
+        unreachable!("There are no 512GiB pages")
+    };
+    let pdp_entry = &mut pdp_table[page.order_index::<Page1GiB>()];
+    let Some(pd_table) = target_or_alloc(pdp_entry, allocator) else {
+        return assert_covers(*pdp_entry, frame, page);
+    };
+    let pd_entry = &mut pd_table[page.order_index::<Page2MiB>()];
+    let Some(pt_table) = target_or_alloc(pd_entry, allocator) else {
+        return assert_covers(*pd_entry, frame, page);
+    };
     let pt_entry = &mut pt_table[page.order_index::<Page4KiB>()];
 
     let mut target = PagingMapEntry::<Page4KiB>::new(frame);
@@ -61,29 +78,33 @@ pub fn map(
 
 pub fn new_root(allocator: &mut PostBootAllocator<ALLOCATOR_CAP>) -> PagingRootEntry {
     let target = allocator
-        .alloc([PagingRawEntry::<Page512GiB>::new(0); 512])
+        .alloc(
+            [PagingRawEntry::<Page512GiB>::new(0); 512],
+            MemoryKind::PageTables,
+        )
         .expect("Out of memory");
     PagingRootEntry::new(Frame::containing(PhysAddr::new_panic(
         target as *const _ as usize,
     )))
 }
 
+/// `None` if `entry` maps a large page instead
 fn target_or_alloc<'a, PS>(
     entry: &mut PagingRawEntry<PS>,
     allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
-) -> &'a mut [PagingRawEntry<PS::ReferenceTarget>]
+) -> Option<&'a mut [PagingRawEntry<PS::ReferenceTarget>]>
 where
     PS: PageSize,
     PS::ReferenceTarget: PageSize,
 {
     if let Some(entry_reference) = entry.as_reference() {
-        unsafe {
+        Some(unsafe {
             // SAFETY: trust in the process
             entry_reference.target_mut()
-        }
+        })
     } else if entry.as_absent().is_some() {
         let target = allocator
-            .alloc([PagingRawEntry::new(0); 512])
+            .alloc([PagingRawEntry::new(0); 512], MemoryKind::PageTables)
             .expect("Out of memory");
         let reference = PagingReferenceEntry::<PS>::new(Frame::containing(PhysAddr::new_panic(
             target as *const _ as usize,
@@ -92,66 +113,250 @@ where
         .exec()
         .to_raw();
         *entry = reference;
-        target
+        Some(target)
     } else {
-        unimplemented!()
+        None
+    }
+}
+
+/// Mapping a 4KiB page inside a large page is only fine if it already maps `frame` there,
+/// e.g. the RSDP in the offset mapping
+fn assert_covers<PS>(entry: PagingRawEntry<PS>, frame: Frame<Frame4KiB>, page: Page<Page4KiB>)
+where
+    PS: PageSize,
+    PS::PhysicalPageSize: FrameSize,
+{
+    let large = entry.as_map().expect("Not a large page");
+    let mapped = large.target_frame().boundary() + (page.boundary().as_usize() & !PS::MASK);
+    if mapped != frame.boundary() {
+        panic!(
+            "{:#x} is already mapped to {:#x} by a large page",
+            page.boundary().as_usize(),
+            mapped.as_usize()
+        );
     }
 }
 
+/// Maps `phys` at `virt` with the largest pages their alignment allows, only falling back to
+/// smaller pages where smaller pages are already mapped
+pub fn map_region(
+    root: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+    phys: PhysicalMemoryRegion,
+    virt: VirtAddr,
+    write: bool,
+    exec: bool,
+    mtype: PatMemoryType,
+) {
+    let size = *phys.size();
+    let mut offset = 0;
+    while offset < size {
+        let frame = phys.start() + offset;
+        let page = Page::<Page4KiB>::containing(virt + offset);
+        let left = size - offset;
+        let fits = |page_size: usize| {
+            left >= page_size
+                && frame.as_usize() % page_size == 0
+                && page.boundary().as_usize() % page_size == 0
+        };
+        let pml4_table = unsafe { root.target_mut() };
+        let Some(pdp_table) = target_or_alloc(
+            pml4_table[page.order_index::<Page512GiB>()].as_raw(),
+            allocator,
+        ) else {
+            unreachable!("There are no 512GiB pages")
+        };
+        let pdp_entry = &mut pdp_table[page.order_index::<Page1GiB>()];
+        if fits(Page1GiB::SIZE) && map_large(pdp_entry, frame, write, exec, mtype) {
+            offset += Page1GiB::SIZE;
+            continue;
+        }
+        if fits(Page2MiB::SIZE) {
+            if let Some(pd_table) = target_or_alloc(pdp_entry, allocator) {
+                let pd_entry = &mut pd_table[page.order_index::<Page2MiB>()];
+                if map_large(pd_entry, frame, write, exec, mtype) {
+                    offset += Page2MiB::SIZE;
+                    continue;
+                }
+            }
+        }
+        map(
+            root,
+            allocator,
+            Frame::containing(frame),
+            page,
+            write,
+            exec,
+            mtype,
+        );
+        offset += Page4KiB::SIZE;
+    }
+}
+
+/// Maps `frame` with a single large page if `entry` is free
+fn map_large<PS>(
+    entry: &mut PagingRawEntry<PS>,
+    frame: PhysAddr,
+    write: bool,
+    exec: bool,
+    mtype: PatMemoryType,
+) -> bool
+where
+    PS: PageSize,
+    PS::PhysicalPageSize: FrameSize,
+{
+    if entry.as_absent().is_none() {
+        return false;
+    }
+    let mut target = PagingMapEntry::<PS>::new(Frame::containing(frame));
+    if write {
+        target = target.write();
+    }
+    if exec {
+        target = target.exec();
+    }
+    *entry = target.with_pat_index(pat_index(mtype)).to_raw();
+    true
+}
+
+/// Maps RAM at its own address, and RAM and ACPI memory at `offset`, see
+/// [config::vmem::PHYSICAL_MAPPING_REGION]. Contiguous entries are mapped together,
+/// so large pages can span them, unless one of them can't be cached write-back.
 pub fn identity_and_offset_mapping(
     allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
     mmap: &MemoryMapOwned,
     offset: usize,
 ) -> PagingRootEntry {
     let root_map = new_root(allocator);
-    // TODO: Setup virtual memory for kernel
-
-    for entry in mmap.entries() {
-        let region = PhysicalMemoryRegion::new(
-            PhysAddr::new_panic(entry.phys_start as usize),
-            MemorySize::new(entry.page_count as usize * 4096),
-        );
-        if entry.phys_start < 1024 * 1024
-            || (entry.ty != MemoryType::CONVENTIONAL
-                && entry.ty != MemoryType::LOADER_CODE
-                && entry.ty != MemoryType::LOADER_DATA
-                && entry.ty != MemoryType::BOOT_SERVICES_CODE
-                && entry.ty != MemoryType::BOOT_SERVICES_DATA)
-        {
-            continue;
-        }
+    let mappable = PHYSICAL_MAPPING_REGION.end().as_usize() - offset;
 
-        let exec = entry.ty == MemoryType::LOADER_CODE;
-
-        let pg_count = *region.size() / 4096;
-        for i in 0..pg_count {
-            let frame = Frame::containing(region.start() + i * 4096);
-            let identity_vaddr = region.start().to_virt() + i * 4096;
-            let offset_vadr = identity_vaddr + offset;
-            let page = Page::containing(identity_vaddr);
-            let offset_page = Page::containing(offset_vadr);
-            map(
+    for_each_run(
+        mmap,
+        |entry| (is_ram(entry) || is_acpi(entry)).then_some(memory_type(entry)),
+        |region, mtype| {
+            // Anything past the physical mapping region is left out, like the kernel ignores it
+            let region = region.intersect(PhysicalMemoryRegion::new(
+                PhysAddr::null(),
+                MemorySize::new(mappable),
+            ));
+            if region.is_null() {
+                return;
+            }
+            map_region(
                 root_map,
                 allocator,
-                frame,
-                page,
+                region,
+                region.start().to_virt() + offset,
                 true,
-                exec,
-                PatMemoryType::WriteBack,
-            );
-            map(
+                false,
+                mtype,
+            )
+        },
+    );
+    for_each_run(
+        mmap,
+        |entry| is_ram(entry).then_some((entry.ty == MemoryType::LOADER_CODE, memory_type(entry))),
+        |region, (exec, mtype)| {
+            map_region(
                 root_map,
                 allocator,
-                frame,
-                offset_page,
+                region,
+                region.start().to_virt(),
                 true,
-                false,
-                PatMemoryType::WriteBack,
-            );
+                exec,
+                mtype,
+            )
+        },
+    );
+
+    root_map
+}
+
+/// End of the memory [identity_and_offset_mapping] and [map_rsdp] map at the offset
+pub fn offset_mapping_end(mmap: &MemoryMapOwned, acpi: &AcpiInfo) -> PhysAddr {
+    mmap.entries()
+        .filter(|entry| is_ram(entry) || is_acpi(entry))
+        .map(|entry| entry.phys_start as usize + entry.page_count as usize * 4096)
+        .chain([acpi.rsdp.as_usize() + mem::size_of::<Rsdp>()])
+        .max()
+        .map_or(PhysAddr::null(), PhysAddr::new_panic)
+}
+
+fn is_ram(entry: &MemoryDescriptor) -> bool {
+    entry.phys_start >= 1024 * 1024
+        && (entry.ty == MemoryType::CONVENTIONAL
+            || entry.ty == MemoryType::LOADER_CODE
+            || entry.ty == MemoryType::LOADER_DATA
+            || entry.ty == MemoryType::BOOT_SERVICES_CODE
+            || entry.ty == MemoryType::BOOT_SERVICES_DATA
+            // The bootloader keeps logging to it until it cedes control
+            || entry.ty == BOOT_LOG_MEMORY)
+}
+
+/// The kernel parses ACPI tables through the offset mapping only
+fn is_acpi(entry: &MemoryDescriptor) -> bool {
+    entry.ty == MemoryType::ACPI_RECLAIM || entry.ty == MemoryType::ACPI_NON_VOLATILE
+}
+
+/// Write-back unless the firmware says the entry can't be cached that way, a large page must
+/// not span both kinds
+fn memory_type(entry: &MemoryDescriptor) -> PatMemoryType {
+    if entry.att.contains(MemoryAttribute::WRITE_BACK) {
+        PatMemoryType::WriteBack
+    } else {
+        PatMemoryType::Uncacheable
+    }
+}
+
+/// Calls `f` with every run of contiguous entries `kind` gives the same `Some` for
+fn for_each_run<K: PartialEq + Copy>(
+    mmap: &MemoryMapOwned,
+    kind: impl Fn(&MemoryDescriptor) -> Option<K>,
+    mut f: impl FnMut(PhysicalMemoryRegion, K),
+) {
+    let mut run: Option<(PhysicalMemoryRegion, K)> = None;
+    for entry in mmap.entries() {
+        let region = PhysicalMemoryRegion::new(
+            PhysAddr::new_panic(entry.phys_start as usize),
+            MemorySize::new(entry.page_count as usize * 4096),
+        );
+        let entry_kind = kind(entry);
+        if let Some((current, current_kind)) = &mut run {
+            if entry_kind == Some(*current_kind) && current.end() == region.start() {
+                *current += region;
+                continue;
+            }
+            f(*current, *current_kind);
         }
+        run = entry_kind.map(|entry_kind| (region, entry_kind));
+    }
+    if let Some((region, kind)) = run {
+        f(region, kind);
     }
+}
 
-    root_map
+/// Maps the RSDP at `offset` like the rest of ACPI memory, firmwares may put it elsewhere
+pub fn map_rsdp(
+    acpi: &AcpiInfo,
+    root_map: PagingRootEntry,
+    allocator: &mut PostBootAllocator<ALLOCATOR_CAP>,
+    offset: usize,
+) {
+    let start = Frame::<Frame4KiB>::containing(acpi.rsdp);
+    let end = Frame::<Frame4KiB>::containing(acpi.rsdp + (mem::size_of::<Rsdp>() - 1));
+    for number in start.number()..=end.number() {
+        let frame = Frame::<Frame4KiB>::from_number(number);
+        let page = Page::containing(frame.boundary().to_virt() + offset);
+        map(
+            root_map,
+            allocator,
+            frame,
+            page,
+            true,
+            false,
+            PatMemoryType::WriteBack,
+        );
+    }
 }
 
 pub fn map_bootinfo(
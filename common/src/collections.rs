@@ -1 +1,2 @@
-pub mod smallvec;
\ No newline at end of file
+pub mod freelist;
+pub mod smallvec;
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod test;
+
+use core::ops::Range;
+
+/// Free address ranges for a first fit allocator
+///
+/// Ranges are kept sorted by address, with a segment tree of the largest free range over them,
+/// so finding a range big enough takes O(log n).
+/// Ranges are only split for aligned allocations not starting at the range start.
+pub struct FreeList<const MAX: usize> {
+    /// Sorted by address. Exhausted ranges keep their slot, as empty ranges at the address
+    /// they ended, until [FreeList::compact] needs it.
+    ranges: [Range<usize>; MAX],
+    len: usize,
+    /// Node 1 is the root, node `i` has nodes `2i` and `2i + 1` as children.
+    /// Nodes `MAX..2 * MAX` are the slots of `ranges`, see [FreeList::node].
+    largest: [usize; MAX],
+}
+
+impl<const MAX: usize> FreeList<MAX> {
+    /// `ranges` must be sorted by address and not overlap, panics if there are more than `MAX`
+    pub fn new(ranges: impl IntoIterator<Item = Range<usize>>) -> Self {
+        const { assert!(MAX >= 2 && MAX.is_power_of_two()) };
+        let mut list = Self {
+            ranges: [const { 0..0 }; MAX],
+            len: 0,
+            largest: [0; MAX],
+        };
+        for range in ranges {
+            assert!(list.len < MAX, "Too many free ranges");
+            debug_assert!(list.len == 0 || list.ranges[list.len - 1].end <= range.start);
+            list.ranges[list.len] = range;
+            list.len += 1;
+        }
+        list.rebuild();
+        list
+    }
+}
+
+impl<const MAX: usize> FreeList<MAX> {
+    /// Ranges left, sorted by address
+    pub fn iter(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.ranges[..self.len]
+            .iter()
+            .filter(|range| !range.is_empty())
+            .cloned()
+    }
+    /// Allocates from the first of `zones` with room, see [FreeList::alloc_in]
+    pub fn alloc(
+        &mut self,
+        zones: impl IntoIterator<Item = Range<usize>>,
+        size: usize,
+        align: usize,
+    ) -> Option<usize> {
+        zones
+            .into_iter()
+            .find_map(|zone| self.alloc_in(zone, size, align))
+    }
+    /// Start of `size` bytes aligned to `align` inside `zone`, at the lowest address possible.
+    /// `align` must be a power of two.
+    pub fn alloc_in(&mut self, zone: Range<usize>, size: usize, align: usize) -> Option<usize> {
+        debug_assert!(align.is_power_of_two());
+        let slots = &self.ranges[..self.len];
+        let from = slots.partition_point(|range| range.end <= zone.start);
+        let to = slots.partition_point(|range| range.start < zone.end);
+        // Only ranges crossing the zone boundaries or misaligned ones can be skipped
+        let mut next = from;
+        while let Some(slot) = self.first_fit(1, 0, MAX, next..to, size) {
+            let range = &self.ranges[slot];
+            let (start, end) = (range.start.max(zone.start), range.end.min(zone.end));
+            if let Some(aligned) = start.checked_next_multiple_of(align) {
+                if aligned
+                    .checked_add(size)
+                    .is_some_and(|alloc_end| alloc_end <= end)
+                {
+                    self.take(slot, aligned..aligned + size);
+                    return Some(aligned);
+                }
+            }
+            next = slot + 1;
+        }
+        None
+    }
+}
+
+impl<const MAX: usize> FreeList<MAX> {
+    /// Cuts `allocated` out of the range in `slot`
+    fn take(&mut self, slot: usize, allocated: Range<usize>) {
+        let range = self.ranges[slot].clone();
+        let tail = allocated.end..range.end;
+        if allocated.start == range.start {
+            self.ranges[slot] = tail;
+            self.update(slot);
+        } else {
+            self.ranges[slot] = range.start..allocated.start;
+            self.update(slot);
+            if !tail.is_empty() {
+                self.insert(tail);
+            }
+        }
+    }
+    /// Adds a range split off another one, O(n)
+    fn insert(&mut self, range: Range<usize>) {
+        if self.len == MAX {
+            self.compact();
+            if self.len == MAX {
+                panic!("Too many free ranges to keep track of");
+            }
+        }
+        let at = self.ranges[..self.len].partition_point(|other| other.start < range.start);
+        for slot in (at..self.len).rev() {
+            self.ranges[slot + 1] = self.ranges[slot].clone();
+        }
+        self.ranges[at] = range;
+        self.len += 1;
+        self.rebuild();
+    }
+    /// Frees the slots of exhausted ranges, O(n)
+    fn compact(&mut self) {
+        let mut len = 0;
+        for slot in 0..self.len {
+            if !self.ranges[slot].is_empty() {
+                self.ranges[len] = self.ranges[slot].clone();
+                len += 1;
+            }
+        }
+        self.ranges[len..].fill(0..0);
+        self.len = len;
+        self.rebuild();
+    }
+}
+
+impl<const MAX: usize> FreeList<MAX> {
+    /// Largest free range under `node`
+    #[inline]
+    fn node(&self, node: usize) -> usize {
+        if node >= MAX {
+            self.ranges[node - MAX].len()
+        } else {
+            self.largest[node]
+        }
+    }
+    fn update(&mut self, slot: usize) {
+        let mut node = (slot + MAX) / 2;
+        while node > 0 {
+            self.largest[node] = self.node(2 * node).max(self.node(2 * node + 1));
+            node /= 2;
+        }
+    }
+    fn rebuild(&mut self) {
+        for node in (1..MAX).rev() {
+            self.largest[node] = self.node(2 * node).max(self.node(2 * node + 1));
+        }
+    }
+    /// First slot in `range` of at least `size` bytes, under `node` which covers `len` slots from `start`
+    fn first_fit(
+        &self,
+        node: usize,
+        start: usize,
+        len: usize,
+        range: Range<usize>,
+        size: usize,
+    ) -> Option<usize> {
+        if start >= range.end || start + len <= range.start || self.node(node) < size {
+            return None;
+        }
+        if len == 1 {
+            return Some(start);
+        }
+        let half = len / 2;
+        self.first_fit(2 * node, start, half, range.clone(), size)
+            .or_else(|| self.first_fit(2 * node + 1, start + half, half, range, size))
+    }
+}
@@ -0,0 +1,118 @@
+use super::FreeList;
+use core::iter;
+use core::ops::Range;
+
+const ALL: Range<usize> = 0..usize::MAX;
+
+fn ranges<const MAX: usize>(list: &FreeList<MAX>) -> ([Range<usize>; MAX], usize) {
+    let mut ranges = [const { 0..0 }; MAX];
+    let mut len = 0;
+    for range in list.iter() {
+        ranges[len] = range;
+        len += 1;
+    }
+    (ranges, len)
+}
+
+fn assert_ranges<const MAX: usize>(list: &FreeList<MAX>, expected: &[Range<usize>]) {
+    let (ranges, len) = ranges(list);
+    assert_eq!(&ranges[..len], expected);
+}
+
+#[test]
+fn test_first_fit() {
+    let mut list = FreeList::<4>::new([0x1000..0x3000, 0x10000..0x20000]);
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x1000), Some(0x1000));
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x1000), Some(0x2000));
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x1000), Some(0x10000));
+    // Only the largest range is big enough
+    assert_eq!(list.alloc_in(ALL, 0xF000, 1), Some(0x11000));
+    assert_eq!(list.alloc_in(ALL, 1, 1), None);
+    assert_ranges(&list, &[]);
+}
+
+#[test]
+fn test_zones() {
+    let mut list = FreeList::<4>::new([0..0x10000, 0x100000..0x200000]);
+    let zones = [0x100000..0x180000, 0..0x10000];
+    assert_eq!(list.alloc(zones.clone(), 0x80000, 1), Some(0x100000));
+    // The rest of the range is past the end of the first zone, and too big for the second
+    assert_eq!(list.alloc(zones.clone(), 0x80000, 1), None);
+    assert_eq!(list.alloc(zones.clone(), 0x1000, 1), Some(0));
+    assert_ranges(&list, &[0x1000..0x10000, 0x180000..0x200000]);
+
+    // Ranges crossing the zone boundaries only give what is inside
+    assert_eq!(list.alloc_in(0x8000..0x190000, 0x8000, 1), Some(0x8000));
+    assert_eq!(list.alloc_in(0x8000..0x190000, 0x10000, 1), Some(0x180000));
+    assert_eq!(list.alloc_in(0x8000..0x190000, 1, 1), None);
+    assert_ranges(&list, &[0x1000..0x8000, 0x190000..0x200000]);
+    assert_eq!(list.alloc_in(0x300000..0x400000, 1, 1), None);
+}
+
+#[test]
+fn test_alignment() {
+    let mut list = FreeList::<4>::new([0x1000..0x400000, usize::MAX - 0xFFF..usize::MAX]);
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x200000), Some(0x200000));
+    assert_ranges(
+        &list,
+        &[
+            0x1000..0x200000,
+            0x201000..0x400000,
+            usize::MAX - 0xFFF..usize::MAX,
+        ],
+    );
+    // Aligning past the end of the range, or of the address space
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x400000), None);
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1 << 63), None);
+    assert_eq!(list.alloc_in(ALL, 0x1000, 0x1000), Some(0x1000));
+}
+
+#[test]
+fn test_splits() {
+    let mut list = FreeList::<4>::new(iter::once(0..0x10000));
+    // Head and tail
+    assert_eq!(list.alloc_in(0x4000..ALL.end, 0x1000, 1), Some(0x4000));
+    assert_ranges(&list, &[0..0x4000, 0x5000..0x10000]);
+    // Head only, nothing is inserted for the empty tail
+    assert_eq!(list.alloc_in(0xF000..ALL.end, 0x1000, 1), Some(0xF000));
+    assert_ranges(&list, &[0..0x4000, 0x5000..0xF000]);
+    // Tail only
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0));
+    assert_ranges(&list, &[0x1000..0x4000, 0x5000..0xF000]);
+    assert_eq!(list.len, 2);
+    // The split ranges are in the tree
+    assert_eq!(list.alloc_in(ALL, 0xA000, 1), Some(0x5000));
+    assert_eq!(list.alloc_in(ALL, 0x3000, 1), Some(0x1000));
+    assert_ranges(&list, &[]);
+}
+
+#[test]
+fn test_compact() {
+    let mut list = FreeList::<4>::new([0..0x1000, 0x2000..0x3000, 0x4000..0x5000, 0x6000..0x9000]);
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0));
+    // Exhausted ranges keep their slot until a split needs it
+    assert_eq!(list.len, 4);
+    assert_eq!(list.alloc_in(0x7000..ALL.end, 0x1000, 1), Some(0x7000));
+    assert_eq!(list.len, 4);
+    assert_ranges(
+        &list,
+        &[
+            0x2000..0x3000,
+            0x4000..0x5000,
+            0x6000..0x7000,
+            0x8000..0x9000,
+        ],
+    );
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0x2000));
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0x4000));
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0x6000));
+    assert_eq!(list.alloc_in(ALL, 0x1000, 1), Some(0x8000));
+    assert_eq!(list.alloc_in(ALL, 1, 1), None);
+}
+
+#[test]
+#[should_panic(expected = "Too many free ranges to keep track of")]
+fn test_full() {
+    let mut list = FreeList::<2>::new([0..0x1000, 0x2000..0x3000]);
+    list.alloc_in(0x800..ALL.end, 0x100, 1);
+}
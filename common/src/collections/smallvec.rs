@@ -54,6 +54,16 @@ impl<T, const N: usize> SmallVec<T, N> {
             self.buffer[self.len].assume_init_read()
         })
     }
+    /// Whether the length fits the buffer, for vectors that were not built by this code
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.len <= N
+    }
+    /// Start of the buffer, the first `len` elements are initialized
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.buffer.as_ptr().cast()
+    }
     pub fn erase(&mut self, index: usize) -> Option<T> {
         if self.len <= index {
             return None;
@@ -146,6 +156,18 @@ impl<T: Debug, const N: usize> Debug for SmallVec<T, N> {
     }
 }
 
+impl<T: Clone, const N: usize> Clone for SmallVec<T, N> {
+    fn clone(&self) -> Self {
+        let mut clone = Self::new();
+        for value in self {
+            if clone.push(value.clone()).is_err() {
+                unreachable!("Same capacity");
+            }
+        }
+        clone
+    }
+}
+
 impl<T, const N: usize> Default for SmallVec<T, N> {
     fn default() -> Self {
         Self::new()
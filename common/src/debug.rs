@@ -1,19 +1,41 @@
+use core::fmt;
 use log::debug;
 
+const BYTE_PER_LINE: usize = 16;
+
 /// Dump memory with 32 byte per line
 pub fn memory_dump(start: usize, size: usize) {
-    const BYTE_PER_LINE: usize = 16;
-    let aligned_start = start / BYTE_PER_LINE * BYTE_PER_LINE;
-    let end = start + size;
-    let aligned_end = end.next_multiple_of(BYTE_PER_LINE);
-    let start = aligned_start;
-    let end = aligned_end;
-    let size = end - start;
-
+    let (start, end) = align_range(start, size);
     debug!(
         "Memory dump {:016x}:{:016x} {:#016x} bytes",
-        start, end, size
+        start,
+        end,
+        end - start
     );
+    dump_lines(start, end, |line_start, line| {
+        debug!("{:016x}: {}", line_start, line);
+    });
+}
+
+/// Same as [memory_dump], to `out` instead of the logger, e.g. when panicking
+pub fn write_memory_dump(out: &mut impl fmt::Write, start: usize, size: usize) -> fmt::Result {
+    let (start, end) = align_range(start, size);
+    let mut result = Ok(());
+    dump_lines(start, end, |line_start, line| {
+        if result.is_ok() {
+            result = writeln!(out, "{:016x}: {}", line_start, line);
+        }
+    });
+    result
+}
+
+fn align_range(start: usize, size: usize) -> (usize, usize) {
+    let aligned_start = start / BYTE_PER_LINE * BYTE_PER_LINE;
+    let aligned_end = (start + size).next_multiple_of(BYTE_PER_LINE);
+    (aligned_start, aligned_end)
+}
+
+fn dump_lines(start: usize, end: usize, mut f: impl FnMut(usize, &str)) {
     for line_start in (start..end).step_by(BYTE_PER_LINE) {
         let mut line = [0u8; BYTE_PER_LINE * 2 + BYTE_PER_LINE];
         let mut head = 0;
@@ -29,11 +51,7 @@ pub fn memory_dump(start: usize, size: usize) {
             line[head + 2] = b' ';
             head += 3;
         }
-        debug!(
-            "{:016x}: {}",
-            line_start,
-            str::from_utf8(&line[..head]).unwrap()
-        );
+        f(line_start, str::from_utf8(&line[..head]).unwrap());
     }
 }
 
@@ -1,2 +1,11 @@
 pub const MAX_HART_COUNT: usize = 16;
 pub const MAX_INTCTL_COUNT: usize = 16;
+
+/// Size of the main kernel stack of each hart, see [KSTACK_REGION](crate::vmem::KSTACK_REGION)
+pub const KSTACK_SIZE: usize = 512 * 1024;
+/// Unmapped gap below each main kernel stack, so that an overflow faults instead of
+/// running into the stack of another hart
+pub const KSTACK_GUARD_SIZE: usize = 4096;
+/// Unmapped gap below each kernel TLS block, see [KTLS_REGION](crate::vmem::KTLS_REGION), so
+/// that a stray access past the start of a block faults instead of reading another hart's
+pub const KTLS_GUARD_SIZE: usize = 4096;
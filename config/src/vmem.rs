@@ -3,7 +3,7 @@
 //! Virtual memory is divided into 3 parts
 //! - [Userspace](USERSPACE_REGION): Contains currently running process and is different per hart at any given point in time
 //! - [Shared Kernel space](KERNEL_SHARED_REGION): Common to all harts, contains the global kernel heap, physical mapping, the kernel, ...
-//! - [Local Kernel space](KERNEL_LOCAL_REGION): Contains hart specific data, such as the stack, local APIC mapping, local heap, ...
+//! - [Local Kernel space](KERNEL_LOCAL_REGION): Contains hart specific data, such as the stack, thread-locals, local APIC mapping, local heap, ...
 
 // TODO: proc macro to do this in a tree structure easily
 // I'm dreaming, but maybe I should make a crate which shows this as graphs
@@ -17,6 +17,7 @@ use x64::mem::addr::VirtAddr;
 const B0: MemorySize = MemorySize::zero();
 const G8: MemorySize = MemorySize::new(0x200000000);
 const G16: MemorySize = MemorySize::new(2 * G8.as_usize());
+const G24: MemorySize = MemorySize::new(3 * G8.as_usize());
 const G512: MemorySize = MemorySize::new(32 * G16.as_usize());
 const T1: MemorySize = MemorySize::new(2 * G512.as_usize());
 const T64: MemorySize = MemorySize::new(64 * T1.as_usize());
@@ -80,9 +81,13 @@ pub const KSTACK_REGION: VirtualMemoryRegion = firstof(KERNEL_LOCAL_REGION, G8,
 /// Always WriteBack memory type. Not subject to swapping policies.
 pub const SPECIAL_KSTACK_REGION: VirtualMemoryRegion = after(KSTACK_REGION, G8, B0, B0);
 
+/// Thread-local storage blocks of the kernel, one per hart, the FS base of each hart points in its own.
+/// Always WriteBack memory type. Not subject to swapping policies.
+pub const KTLS_REGION: VirtualMemoryRegion = after(SPECIAL_KSTACK_REGION, G8, B0, B0);
+
 /// Hart local heap. Only pages actually used are mapped. Always uses the WriteBack memory type.
 /// Subject to swapping policies.
-pub const LOCAL_HEAP_REGION: VirtualMemoryRegion = after(SPECIAL_KSTACK_REGION, G512, B0, G16);
+pub const LOCAL_HEAP_REGION: VirtualMemoryRegion = after(KTLS_REGION, G512, B0, G24);
 
 /// Used by drivers which provide MMIO that should only be accessed through a single hart.
 ///
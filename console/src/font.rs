@@ -0,0 +1,105 @@
+//! PC Screen Font (PSF) version 1 and 2 bitmap fonts.
+//!
+//! [DEFAULT_FONT] is DejaVu Sans Mono rasterized to 8x16 cells, covering printable ASCII,
+//! glyph 0 is a box used for everything else.
+//! DejaVu fonts are under the Bitstream Vera license, see `font/LICENSE`, glyph changes are in
+//! the public domain.
+
+#[cfg(test)]
+mod test;
+
+pub static DEFAULT_FONT: &[u8] = include_bytes!("font/default.psf");
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF2_MAGIC: u32 = 0x864a_b572;
+
+#[derive(Clone, Copy)]
+pub struct PsfFont {
+    glyphs: &'static [u8],
+    glyph_count: usize,
+    bytes_per_glyph: usize,
+    width: usize,
+    height: usize,
+}
+
+impl PsfFont {
+    /// Returns None if `data` is not a well formed PSF font
+    pub fn parse(data: &'static [u8]) -> Option<Self> {
+        if data.get(..2)? == PSF1_MAGIC {
+            let mode = *data.get(2)?;
+            let height = *data.get(3)? as usize;
+            let glyph_count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+            return Self::new(data.get(4..)?, glyph_count, height, 8, height);
+        }
+        let field = |i: usize| {
+            let bytes = data.get(i * 4..i * 4 + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        };
+        if field(0)? != PSF2_MAGIC as usize {
+            return None;
+        }
+        let header_size = field(2)?;
+        Self::new(
+            data.get(header_size..)?,
+            field(4)?,
+            field(5)?,
+            field(7)?,
+            field(6)?,
+        )
+    }
+
+    pub fn default_font() -> Self {
+        Self::parse(DEFAULT_FONT).expect("Default font is a valid PSF")
+    }
+
+    fn new(
+        glyphs: &'static [u8],
+        glyph_count: usize,
+        bytes_per_glyph: usize,
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        if glyph_count == 0
+            || width == 0
+            || bytes_per_glyph < width.div_ceil(8) * height
+            || glyphs.len() < glyph_count * bytes_per_glyph
+        {
+            return None;
+        }
+        Some(Self {
+            glyphs,
+            glyph_count,
+            bytes_per_glyph,
+            width,
+            height,
+        })
+    }
+}
+
+impl PsfFont {
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Bytes per glyph row
+    #[inline]
+    pub fn pitch(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+    /// `height` rows of [PsfFont::pitch] bytes, most significant bit leftmost.
+    /// Characters without a glyph get the first one, which is usually a placeholder.
+    pub fn glyph(&self, c: char) -> &'static [u8] {
+        let index = if (c as usize) < self.glyph_count {
+            c as usize
+        } else {
+            0
+        };
+        let start = index * self.bytes_per_glyph;
+        &self.glyphs[start..start + self.pitch() * self.height]
+    }
+}
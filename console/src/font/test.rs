@@ -0,0 +1,28 @@
+use super::PsfFont;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[test]
+fn test_default_font() {
+    let font = PsfFont::default_font();
+    assert_eq!((font.width(), font.height()), (8, 16));
+    assert!(font.glyph(' ').iter().all(|&row| row == 0));
+    assert!(font.glyph('A').iter().any(|&row| row != 0));
+    assert_eq!(font.glyph('é'), font.glyph('\0'));
+}
+
+#[test]
+fn test_psf1() {
+    let mut data: Vec<u8> = vec![0x36, 0x04, 0x00, 4];
+    data.extend((0..256u32).flat_map(|i| [i as u8; 4]));
+    let font = PsfFont::parse(data.leak()).unwrap();
+    assert_eq!((font.width(), font.height()), (8, 4));
+    assert_eq!(font.glyph('a'), &[b'a'; 4]);
+}
+
+#[test]
+fn test_invalid() {
+    assert!(PsfFont::parse(&[]).is_none());
+    assert!(PsfFont::parse(&[0x36, 0x04, 0x00, 16, 0xFF]).is_none());
+    assert!(PsfFont::parse(&super::DEFAULT_FONT[..64]).is_none());
+}
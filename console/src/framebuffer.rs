@@ -0,0 +1,229 @@
+#[cfg(test)]
+mod test;
+
+use crate::font::PsfFont;
+use boot_protocol::framebuffer::FramebufferInfo;
+use core::fmt;
+use core::slice;
+use x64::framebuffer::PixelColor;
+use x64::framebuffer::PixelMode;
+
+const TAB_WIDTH: usize = 4;
+
+/// Text console drawing into the back buffer of a framebuffer, then copying what changed to it.
+/// Reading from the framebuffer itself is slow, so scrolling only ever reads the back buffer.
+/// Without a back buffer, it draws straight to the framebuffer.
+pub struct FramebufferConsole {
+    fb: &'static mut [u8],
+    buffer: Option<&'static mut [u8]>,
+    width: usize,
+    height: usize,
+    /// In bytes
+    pitch: usize,
+    bytes_per_pixel: usize,
+    mode: PixelMode,
+    font: PsfFont,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    foreground: PixelColor,
+    background: PixelColor,
+}
+
+impl FramebufferConsole {
+    /// None if not even one character fits, see [FramebufferConsole::from_parts]
+    ///
+    /// # Safety
+    /// `info`'s framebuffer and back buffer must be mapped, and not used by anything else
+    pub unsafe fn new(info: &FramebufferInfo, font: PsfFont) -> Option<Self> {
+        let (fb, buffer) = unsafe {
+            // SAFETY: Guaranteed by the caller
+            (
+                slice::from_raw_parts_mut(info.fb.as_ptr(), info.fb.len()),
+                slice::from_raw_parts_mut(info.buffer.as_ptr(), info.buffer.len()),
+            )
+        };
+        Self::from_parts(
+            fb,
+            Some(buffer),
+            (info.width, info.height),
+            info.stride,
+            info.mode,
+            font,
+        )
+    }
+
+    /// `size` is width then height in pixels, `stride` is in pixels.
+    /// None if the framebuffer is narrower or shorter than one character, or `mode` has no
+    /// pixel size.
+    pub fn from_parts(
+        fb: &'static mut [u8],
+        buffer: Option<&'static mut [u8]>,
+        size: (usize, usize),
+        stride: usize,
+        mode: PixelMode,
+        font: PsfFont,
+    ) -> Option<Self> {
+        let (width, height) = size;
+        let bytes_per_pixel = mode.bytes_per_pixel();
+        let pitch = stride * bytes_per_pixel;
+        assert!(
+            fb.len() >= pitch * height
+                && buffer
+                    .as_ref()
+                    .is_none_or(|buffer| buffer.len() >= pitch * height),
+            "Framebuffer too small for its resolution"
+        );
+        if bytes_per_pixel == 0 || width < font.width() || height < font.height() {
+            return None;
+        }
+        let mut console = Self {
+            fb,
+            buffer,
+            width,
+            height,
+            pitch,
+            bytes_per_pixel,
+            mode,
+            columns: width / font.width(),
+            rows: height / font.height(),
+            font,
+            column: 0,
+            row: 0,
+            foreground: PixelColor(0xCC, 0xCC, 0xCC),
+            background: PixelColor(0, 0, 0),
+        };
+        console.clear();
+        Some(console)
+    }
+}
+
+impl FramebufferConsole {
+    /// In characters, columns then rows
+    #[inline]
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+    /// Column then row of the next character
+    #[inline]
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+    pub fn set_colors(&mut self, foreground: PixelColor, background: PixelColor) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+    pub fn set_foreground(&mut self, foreground: PixelColor) {
+        self.foreground = foreground;
+    }
+    pub fn clear(&mut self) {
+        for y in 0..self.height {
+            self.fill_line(y, self.background);
+        }
+        self.blit_lines(0, self.height);
+        self.column = 0;
+        self.row = 0;
+    }
+    pub fn write_char(&mut self, c: char) {
+        match c {
+            '\n' => self.newline(),
+            '\r' => self.column = 0,
+            '\t' => {
+                let spaces = TAB_WIDTH - self.column % TAB_WIDTH;
+                for _ in 0..spaces {
+                    self.write_char(' ');
+                }
+            }
+            c => {
+                if self.column == self.columns {
+                    self.newline();
+                }
+                self.draw_glyph(c);
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl FramebufferConsole {
+    fn newline(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+            return;
+        }
+        // Scroll by one text row
+        let line_bytes = self.pitch * self.font.height();
+        let text_bytes = line_bytes * self.rows;
+        self.target().copy_within(line_bytes..text_bytes, 0);
+        let last_line = (self.rows - 1) * self.font.height();
+        for y in last_line..last_line + self.font.height() {
+            self.fill_line(y, self.background);
+        }
+        self.blit_lines(0, text_bytes / self.pitch);
+    }
+    fn draw_glyph(&mut self, c: char) {
+        let glyph = self.font.glyph(c);
+        let pitch = self.font.pitch();
+        let foreground = self.encode(self.foreground);
+        let background = self.encode(self.background);
+        let x_start = self.column * self.font.width();
+        let y_start = self.row * self.font.height();
+        for (y, row) in glyph.chunks_exact(pitch).enumerate() {
+            let line = (y_start + y) * self.pitch;
+            for x in 0..self.font.width() {
+                let on = row[x / 8] & (0x80 >> (x % 8)) != 0;
+                let pixel = line + (x_start + x) * self.bytes_per_pixel;
+                let color = if on { &foreground } else { &background };
+                target(self.fb, &mut self.buffer)[pixel..pixel + self.bytes_per_pixel]
+                    .copy_from_slice(&color[..self.bytes_per_pixel]);
+            }
+        }
+        self.blit_lines(y_start, y_start + self.font.height());
+    }
+    fn fill_line(&mut self, y: usize, color: PixelColor) {
+        let color = self.encode(color);
+        let line = y * self.pitch;
+        let end = line + self.width * self.bytes_per_pixel;
+        for pixel in
+            target(self.fb, &mut self.buffer)[line..end].chunks_exact_mut(self.bytes_per_pixel)
+        {
+            pixel.copy_from_slice(&color[..self.bytes_per_pixel]);
+        }
+    }
+    /// Copies pixel lines `start..end` of the back buffer to the framebuffer
+    fn blit_lines(&mut self, start: usize, end: usize) {
+        if let Some(buffer) = &self.buffer {
+            let range = start * self.pitch..end * self.pitch;
+            self.fb[range.clone()].copy_from_slice(&buffer[range]);
+        }
+    }
+    /// Where to draw, the back buffer if there is one
+    #[inline]
+    fn target(&mut self) -> &mut [u8] {
+        target(self.fb, &mut self.buffer)
+    }
+    #[inline]
+    fn encode(&self, color: PixelColor) -> [u8; 4] {
+        color.encode(self.mode).to_le_bytes()
+    }
+}
+
+/// Split from [FramebufferConsole::target], to draw while borrowing the font
+#[inline]
+fn target<'a>(fb: &'a mut [u8], buffer: &'a mut Option<&'static mut [u8]>) -> &'a mut [u8] {
+    match buffer {
+        Some(buffer) => buffer,
+        None => fb,
+    }
+}
+
+impl fmt::Write for FramebufferConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.write_char(c);
+        }
+        Ok(())
+    }
+}
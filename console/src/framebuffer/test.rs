@@ -0,0 +1,125 @@
+use super::FramebufferConsole;
+use crate::font::PsfFont;
+use alloc::vec;
+use core::fmt::Write;
+use x64::framebuffer::PixelBitmask;
+use x64::framebuffer::PixelColor;
+use x64::framebuffer::PixelMode;
+
+const WIDTH: usize = 4 * 8;
+const HEIGHT: usize = 3 * 16;
+const STRIDE: usize = WIDTH + 2;
+
+fn console() -> FramebufferConsole {
+    let buffer = vec![0u8; STRIDE * HEIGHT * 4].leak();
+    console_with(Some(buffer))
+}
+
+fn console_with(buffer: Option<&'static mut [u8]>) -> FramebufferConsole {
+    let fb = vec![0xAAu8; STRIDE * HEIGHT * 4].leak();
+    FramebufferConsole::from_parts(
+        fb,
+        buffer,
+        (WIDTH, HEIGHT),
+        STRIDE,
+        PixelMode::RgbRs,
+        PsfFont::default_font(),
+    )
+    .unwrap()
+}
+
+/// Whether the character cell at `column`, `row` has any foreground pixel
+fn cell_drawn(console: &FramebufferConsole, column: usize, row: usize) -> bool {
+    let background = console.encode(console.background);
+    (0..16).any(|y| {
+        (0..8).any(|x| {
+            let pixel = (row * 16 + y) * STRIDE * 4 + (column * 8 + x) * 4;
+            console.fb[pixel..pixel + 4] != background
+        })
+    })
+}
+
+#[test]
+fn test_too_small() {
+    for (width, height) in [(7, HEIGHT), (WIDTH, 15), (0, 0)] {
+        let fb = vec![0u8; STRIDE * HEIGHT * 4].leak();
+        let console = FramebufferConsole::from_parts(
+            fb,
+            None,
+            (width, height),
+            STRIDE,
+            PixelMode::RgbRs,
+            PsfFont::default_font(),
+        );
+        assert!(console.is_none());
+    }
+}
+
+#[test]
+fn test_no_pixel_size() {
+    let fb = vec![0u8; STRIDE * HEIGHT * 4].leak();
+    let mode = PixelMode::Bitmask(PixelBitmask {
+        red: 0,
+        green: 0,
+        blue: 0,
+        reserved: 0,
+    });
+    let console = FramebufferConsole::from_parts(
+        fb,
+        None,
+        (WIDTH, HEIGHT),
+        STRIDE,
+        mode,
+        PsfFont::default_font(),
+    );
+    assert!(console.is_none());
+}
+
+#[test]
+fn test_clear() {
+    let console = console();
+    assert_eq!(console.size(), (4, 3));
+    assert_eq!(console.buffer.as_deref(), Some(&*console.fb));
+    for row in 0..3 {
+        for column in 0..4 {
+            assert!(!cell_drawn(&console, column, row));
+        }
+    }
+}
+
+#[test]
+fn test_write() {
+    let mut console = console();
+    console.set_colors(PixelColor(0xFF, 0, 0), PixelColor(0, 0, 0xFF));
+    write!(console, "A B\n\tC").unwrap();
+    assert_eq!(console.cursor(), (1, 2));
+    assert!(cell_drawn(&console, 0, 0));
+    assert!(!cell_drawn(&console, 1, 0));
+    assert!(cell_drawn(&console, 2, 0));
+    assert!(cell_drawn(&console, 0, 2));
+    assert_eq!(console.buffer.as_deref(), Some(&*console.fb));
+}
+
+#[test]
+fn test_wrap_and_scroll() {
+    let mut console = console();
+    write!(console, "X\n\nYYYYZ").unwrap();
+    // "X" scrolled out, the "Z" wrapped on the last row
+    assert_eq!(console.cursor(), (1, 2));
+    assert!(!cell_drawn(&console, 0, 0));
+    assert!(cell_drawn(&console, 3, 1));
+    assert!(cell_drawn(&console, 0, 2));
+    assert!(!cell_drawn(&console, 1, 2));
+    assert_eq!(console.buffer.as_deref(), Some(&*console.fb));
+}
+
+#[test]
+fn test_unbuffered() {
+    let mut console = console_with(None);
+    write!(console, "X\n\nYYYYZ").unwrap();
+    assert_eq!(console.cursor(), (1, 2));
+    assert!(!cell_drawn(&console, 0, 0));
+    assert!(cell_drawn(&console, 3, 1));
+    assert!(cell_drawn(&console, 0, 2));
+    assert!(!cell_drawn(&console, 1, 2));
+}
@@ -0,0 +1,9 @@
+#![no_std]
+
+#[cfg(test)]
+extern crate alloc;
+
+pub mod font;
+pub mod framebuffer;
+pub mod logger;
+pub mod serial;
@@ -0,0 +1,64 @@
+use crate::framebuffer::FramebufferConsole;
+use core::fmt::Write;
+use log::Level;
+use spinlocks::mutex::Mutex;
+use x64::framebuffer::PixelColor;
+
+const TEXT_COLOR: PixelColor = PixelColor(0xCC, 0xCC, 0xCC);
+
+/// Logs to a [FramebufferConsole], once one is given with [ConsoleLogger::set_console]
+pub struct ConsoleLogger {
+    console: Mutex<Option<FramebufferConsole>>,
+}
+
+impl ConsoleLogger {
+    pub const fn new() -> Self {
+        Self {
+            console: Mutex::new(None),
+        }
+    }
+    pub fn set_console(&self, console: FramebufferConsole) {
+        *self.console.lock() = Some(console);
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.console.lock().is_some()
+    }
+}
+
+impl Default for ConsoleLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        // Don't deadlock if logging from within the logger, e.g. in a panic
+        let Some(mut console) = self.console.try_lock() else {
+            return;
+        };
+        let Some(console) = console.as_mut() else {
+            return;
+        };
+        console.set_foreground(level_color(record.level()));
+        let _ = write!(console, "[{:>5}] ", record.level());
+        console.set_foreground(TEXT_COLOR);
+        let _ = writeln!(console, "{}", record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+fn level_color(level: Level) -> PixelColor {
+    match level {
+        Level::Error => PixelColor(0xFF, 0x55, 0x55),
+        Level::Warn => PixelColor(0xFF, 0xFF, 0x55),
+        Level::Info => PixelColor(0x55, 0xFF, 0x55),
+        Level::Debug => PixelColor(0x55, 0xFF, 0xFF),
+        Level::Trace => PixelColor(0x88, 0x88, 0x88),
+    }
+}
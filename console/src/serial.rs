@@ -0,0 +1,45 @@
+use core::fmt::Write;
+use spinlocks::mutex::Mutex;
+use x64::serial::Uart16550;
+
+/// Logs to a serial port, once one is given with [SerialLogger::set_uart]
+pub struct SerialLogger {
+    uart: Mutex<Option<Uart16550>>,
+}
+
+impl SerialLogger {
+    pub const fn new() -> Self {
+        Self {
+            uart: Mutex::new(None),
+        }
+    }
+    /// `uart` must already be initialized
+    pub fn set_uart(&self, uart: Uart16550) {
+        *self.uart.lock() = Some(uart);
+    }
+}
+
+impl Default for SerialLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl log::Log for SerialLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        // Don't deadlock if logging from within the logger, e.g. in a panic
+        let Some(mut uart) = self.uart.try_lock() else {
+            return;
+        };
+        let Some(uart) = uart.as_mut() else {
+            return;
+        };
+        let _ = writeln!(uart, "[{:>5}] {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
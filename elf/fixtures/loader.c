@@ -0,0 +1,9 @@
+static const char message[] = "PentOS loader fixture";
+int counter = 42;
+char buffer[8192];
+
+void _start(void)
+{
+    for (;;)
+        buffer[counter++ % sizeof(buffer)] = message[counter % sizeof(message)];
+}
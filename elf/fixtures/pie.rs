@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+use core::ptr;
+
+#[unsafe(no_mangle)]
+static mut COUNTER: u64 = 42;
+#[unsafe(no_mangle)]
+static MESSAGE: &str = "PentOS relocation fixture";
+#[unsafe(no_mangle)]
+static COUNTER_PTR: &u64 = unsafe { &*&raw const COUNTER };
+#[unsafe(no_mangle)]
+static HANDLERS: [fn() -> u64; 2] = [first, second];
+
+#[unsafe(no_mangle)]
+fn first() -> u64 {
+    1
+}
+
+#[unsafe(no_mangle)]
+fn second() -> u64 {
+    2
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn _start() -> ! {
+    loop {
+        let handlers = unsafe { ptr::read_volatile(&raw const HANDLERS) };
+        let message = unsafe { ptr::read_volatile(&raw const MESSAGE) };
+        let counter = unsafe { ptr::read_volatile(&raw const COUNTER_PTR) };
+        for handler in handlers {
+            unsafe { COUNTER += handler() + message.len() as u64 + *counter };
+        }
+    }
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
@@ -0,0 +1,23 @@
+int counter = 42;
+const char message[] = "PentOS relocation fixture";
+extern int missing __attribute__((weak));
+
+int *counter_ptr = &counter;
+const char *message_ptr = message;
+int *missing_ptr = &missing;
+
+int get_counter(void)
+{
+    return counter;
+}
+
+int call(void)
+{
+    return get_counter() + 1;
+}
+
+void _start(void)
+{
+    for (;;)
+        counter += call();
+}
@@ -0,0 +1,10 @@
+__thread int counter = 42;
+__thread const char *message = "PentOS TLS fixture";
+__thread char buffer[100];
+__thread long aligned __attribute__((aligned(64)));
+
+void _start(void)
+{
+    for (;;)
+        buffer[counter++ % sizeof(buffer)] = message[counter % 18] + aligned;
+}
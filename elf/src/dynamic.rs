@@ -0,0 +1,131 @@
+//! Dynamic section, see [Elf::dynamic]
+
+use crate::Elf;
+use crate::SegmentType;
+use crate::types::SXWord;
+use crate::types::XWord;
+use core::mem;
+
+const ENTRY_SIZE: usize = 2 * mem::size_of::<XWord>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicTag {
+    Null,
+    Needed,
+    PltRelSize,
+    PltGot,
+    Hash,
+    StrTab,
+    SymTab,
+    Rela,
+    RelaSize,
+    RelaEntry,
+    StrSize,
+    SymEntry,
+    Init,
+    Fini,
+    SoName,
+    RPath,
+    Symbolic,
+    Rel,
+    RelSize,
+    RelEntry,
+    /// [DynamicTag::Rel] or [DynamicTag::Rela], the type of the [DynamicTag::JmpRel] entries
+    PltRel,
+    Debug,
+    TextRel,
+    /// Relocations of the PLT
+    JmpRel,
+    BindNow,
+    Flags,
+    Other(SXWord),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicEntry {
+    pub tag: DynamicTag,
+    /// An address, a size or flags depending on the tag
+    pub value: XWord,
+}
+
+/// Stops at the [DynamicTag::Null] entry, or at the first truncated one
+#[derive(Clone)]
+pub struct DynamicIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Elf<'a> {
+    /// Entries of the PT_DYNAMIC segment, None without one or if it is not in the file
+    pub fn dynamic(&self) -> Option<DynamicIter<'a>> {
+        let segment = self
+            .program_header
+            .into_iter()
+            .find(|segment| segment.ty == SegmentType::Dynamic)?;
+        Some(DynamicIter::new(self.segment_data(&segment)?))
+    }
+}
+
+impl DynamicTag {
+    pub fn parse(tag: SXWord) -> Self {
+        match tag {
+            0 => Self::Null,
+            1 => Self::Needed,
+            2 => Self::PltRelSize,
+            3 => Self::PltGot,
+            4 => Self::Hash,
+            5 => Self::StrTab,
+            6 => Self::SymTab,
+            7 => Self::Rela,
+            8 => Self::RelaSize,
+            9 => Self::RelaEntry,
+            10 => Self::StrSize,
+            11 => Self::SymEntry,
+            12 => Self::Init,
+            13 => Self::Fini,
+            14 => Self::SoName,
+            15 => Self::RPath,
+            16 => Self::Symbolic,
+            17 => Self::Rel,
+            18 => Self::RelSize,
+            19 => Self::RelEntry,
+            20 => Self::PltRel,
+            21 => Self::Debug,
+            22 => Self::TextRel,
+            23 => Self::JmpRel,
+            24 => Self::BindNow,
+            30 => Self::Flags,
+            tag => Self::Other(tag),
+        }
+    }
+}
+
+impl<'a> DynamicIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+    /// Value of the first entry with `tag`
+    pub fn value(&self, tag: DynamicTag) -> Option<XWord> {
+        self.clone()
+            .find(|entry| entry.tag == tag)
+            .map(|entry| entry.value)
+    }
+}
+
+impl Iterator for DynamicIter<'_> {
+    type Item = DynamicEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry, rest) = self.data.split_at_checked(ENTRY_SIZE)?;
+        let (tag, value) = entry.split_at(mem::size_of::<XWord>());
+        let tag = DynamicTag::parse(SXWord::from_ne_bytes(tag.try_into().unwrap()));
+        if tag == DynamicTag::Null {
+            self.data = &[];
+            return None;
+        }
+        self.data = rest;
+        Some(DynamicEntry {
+            tag,
+            value: XWord::from_ne_bytes(value.try_into().unwrap()),
+        })
+    }
+}
@@ -38,6 +38,32 @@ pub struct RawSegment {
     pub alignment: XWord,
 }
 
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawSection {
+    pub name: Word,
+    pub ty: Word,
+    pub flags: XWord,
+    pub addr: Addr,
+    pub offset: Offset,
+    pub size: XWord,
+    pub link: Word,
+    pub info: Word,
+    pub alignment: XWord,
+    pub entry_size: XWord,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawSymbol {
+    pub name: Word,
+    pub info: UChar,
+    pub other: UChar,
+    pub section: Half,
+    pub value: Addr,
+    pub size: XWord,
+}
+
 impl FileHeader {
     pub fn from(data: &[u8]) -> Option<&FileHeader> {
         const HEADER_SIZE: usize = mem::size_of::<FileHeader>();
@@ -67,3 +93,33 @@ impl RawSegment {
         })
     }
 }
+
+impl RawSection {
+    pub fn from(data: &[u8]) -> Option<&RawSection> {
+        const SECTION_SIZE: usize = mem::size_of::<RawSection>();
+        const SECTION_ALIGN: usize = mem::align_of::<RawSection>();
+        if data.len() < SECTION_SIZE || !data.as_ptr().is_aligned_to(SECTION_ALIGN) {
+            return None;
+        }
+
+        Some(unsafe {
+            // SAFETY: `data` is aligned to `SECTION_ALIGN` and has at least length `SECTION_SIZE`. All states are valid.
+            &*(data.as_ptr() as *const _)
+        })
+    }
+}
+
+impl RawSymbol {
+    pub fn from(data: &[u8]) -> Option<&RawSymbol> {
+        const SYMBOL_SIZE: usize = mem::size_of::<RawSymbol>();
+        const SYMBOL_ALIGN: usize = mem::align_of::<RawSymbol>();
+        if data.len() < SYMBOL_SIZE || !data.as_ptr().is_aligned_to(SYMBOL_ALIGN) {
+            return None;
+        }
+
+        Some(unsafe {
+            // SAFETY: `data` is aligned to `SYMBOL_ALIGN` and has at least length `SYMBOL_SIZE`. All states are valid.
+            &*(data.as_ptr() as *const _)
+        })
+    }
+}
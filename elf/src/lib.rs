@@ -1,18 +1,33 @@
 #![no_std]
 #![feature(pointer_is_aligned_to)]
 
+#[cfg(test)]
+extern crate alloc;
+#[cfg(test)]
+mod test;
+
+pub mod dynamic;
 pub mod headers;
+pub mod loader;
+pub mod reloc;
+pub mod section;
+pub mod symbol;
+pub mod tls;
 pub mod types;
 
+use core::mem;
 use core::ops::Index;
 use headers::FileHeader;
+use headers::RawSection;
 use headers::RawSegment;
+use section::SectionHeader;
+use types::Addr;
 use types::Half;
 use types::Offset;
 use types::UChar;
 use types::Word;
-use x64::mem::addr::Address;
 use x64::mem::MemorySize;
+use x64::mem::addr::Address;
 use x64::mem::addr::VirtAddr;
 
 pub struct Elf<'a> {
@@ -21,8 +36,41 @@ pub struct Elf<'a> {
     pub ty: ElfType,
     pub entry: VirtAddr,
     pub program_header: ProgramHeader<'a>,
+    pub section_header: SectionHeader<'a>,
+    /// Index of the section holding section names, see [Elf::section_name]
+    pub section_names: usize,
+}
+
+/// What is wrong with a file [Elf::parse] refuses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// Shorter than the file header
+    TruncatedHeader,
+    /// Not 8 byte aligned in memory
+    Misaligned,
+    BadMagic,
+    /// Only 64-bit files are supported
+    UnsupportedClass(UChar),
+    /// Only little endian files are supported
+    UnsupportedEncoding(UChar),
+    UnsupportedVersion(UChar),
+    UnsupportedAbi(UChar),
+    UnknownType(Half),
+    NonCanonicalEntry(Addr),
+    ProgramHeaderEntrySize(Half),
+    /// The program header table is outside of the file, or misaligned
+    ProgramHeaderOutOfRange,
+    SectionHeaderEntrySize(Half),
+    /// The section header table is outside of the file, or misaligned
+    SectionHeaderOutOfRange,
+    /// The segment at this address is not canonical
+    NonCanonicalSegment(Addr),
+    /// The segment at this address is not at the same place in memory and in the file,
+    /// modulo its alignment
+    MisalignedSegment(Addr),
 }
 
+/// Only 64-bit little endian files get past [Elf::parse]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ElfIdentification {
     pub class: ElfClass,
@@ -57,6 +105,8 @@ pub struct Segment {
     pub vaddr: VirtAddr,
     pub file_size: usize,
     pub mem_size: MemorySize,
+    /// 0 or 1 if the segment needs no alignment
+    pub alignment: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -66,6 +116,9 @@ pub enum SegmentType {
     Dynamic,
     Interpreter,
     Note,
+    Tls,
+    /// OS or processor specific, like PT_GNU_STACK
+    Other(Word),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,6 +128,7 @@ pub struct SegmentFlags {
     pub exec: bool,
 }
 
+/// Every entry is valid when it comes from [Elf::parse]
 pub struct ProgramHeader<'a> {
     raw: &'a [u8],
     entry_size: usize,
@@ -86,37 +140,117 @@ pub struct SegmentIter<'a, 'b> {
     index: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note<'a> {
+    /// Owner of the note, without the terminating NUL
+    pub name: &'a [u8],
+    pub ty: Word,
+    pub desc: &'a [u8],
+}
+
+/// Notes packed in a PT_NOTE segment or SHT_NOTE section, stops at the first malformed one
+pub struct NoteIter<'a> {
+    data: &'a [u8],
+}
+
 impl<'a> Elf<'a> {
-    pub fn parse(data: &'a [u8]) -> Option<Self> {
-        let file_header = FileHeader::from(data)?;
+    /// Checks the file header, and every entry of the program header table
+    pub fn parse(data: &'a [u8]) -> Result<Self, ParseError> {
+        if data.len() < mem::size_of::<FileHeader>() {
+            return Err(ParseError::TruncatedHeader);
+        }
+        let file_header = FileHeader::from(data).ok_or(ParseError::Misaligned)?;
 
         let ident = ElfIdentification::parse(&file_header.ident)?;
-        let ty = ElfType::parse(file_header.ty)?;
-        let entry = VirtAddr::new(file_header.entry as usize)?;
+        let ty = ElfType::parse(file_header.ty).ok_or(ParseError::UnknownType(file_header.ty))?;
+        let entry = VirtAddr::new(file_header.entry as usize)
+            .ok_or(ParseError::NonCanonicalEntry(file_header.entry))?;
+
+        if file_header.phnum != 0 && file_header.phentsize as usize != mem::size_of::<RawSegment>()
+        {
+            return Err(ParseError::ProgramHeaderEntrySize(file_header.phentsize));
+        }
         let program_header = ProgramHeader::new(
-            &data[file_header.phoff as usize..],
+            table::<RawSegment>(
+                data,
+                file_header.phoff,
+                file_header.phentsize,
+                file_header.phnum,
+            )
+            .ok_or(ParseError::ProgramHeaderOutOfRange)?,
             file_header.phentsize as usize,
             file_header.phnum as usize,
         );
+        for index in 0..program_header.len {
+            Segment::parse(&program_header[index])?;
+        }
 
-        Some(Self {
+        // Sections are optional for executables
+        let section_header = if file_header.shoff == 0 || file_header.shnum == 0 {
+            SectionHeader::new(&[], 0, 0)
+        } else {
+            if file_header.shentsize as usize != mem::size_of::<RawSection>() {
+                return Err(ParseError::SectionHeaderEntrySize(file_header.shentsize));
+            }
+            SectionHeader::new(
+                table::<RawSection>(
+                    data,
+                    file_header.shoff,
+                    file_header.shentsize,
+                    file_header.shnum,
+                )
+                .ok_or(ParseError::SectionHeaderOutOfRange)?,
+                file_header.shentsize as usize,
+                file_header.shnum as usize,
+            )
+        };
+
+        Ok(Self {
             data,
             ident,
             ty,
             entry,
             program_header,
+            section_header,
+            section_names: file_header.shstrndx as usize,
         })
     }
 }
 
+impl<'a> Elf<'a> {
+    /// Contents of `segment` in the file
+    pub fn segment_data(&self, segment: &Segment) -> Option<&'a [u8]> {
+        let start = usize::try_from(segment.offset).ok()?;
+        self.data.get(start..start.checked_add(segment.file_size)?)
+    }
+    /// Notes of every PT_NOTE segment
+    pub fn notes(&self) -> impl Iterator<Item = Note<'a>> {
+        self.program_header
+            .into_iter()
+            .filter(|segment| segment.ty == SegmentType::Note)
+            .filter_map(|segment| self.segment_data(&segment))
+            .flat_map(NoteIter::new)
+    }
+}
+
 impl ElfIdentification {
-    pub fn parse(data: &[UChar; 16]) -> Option<Self> {
-        if &data[0..4] != b"\x7FELF" || data[6] != 1 || data[7] != 0 {
-            return None;
+    pub fn parse(data: &[UChar; 16]) -> Result<Self, ParseError> {
+        if &data[0..4] != b"\x7FELF" {
+            return Err(ParseError::BadMagic);
         }
-        let class = ElfClass::parse(data[4])?;
-        let encoding = DataEncoding::parse(data[5])?;
-        Some(Self { class, encoding })
+        let class = ElfClass::parse(data[4])
+            .filter(|&class| class == ElfClass::Elf64)
+            .ok_or(ParseError::UnsupportedClass(data[4]))?;
+        let encoding = DataEncoding::parse(data[5])
+            .filter(|&encoding| encoding == DataEncoding::LittleEndian)
+            .ok_or(ParseError::UnsupportedEncoding(data[5]))?;
+        if data[6] != 1 {
+            return Err(ParseError::UnsupportedVersion(data[6]));
+        }
+        if data[7] != 0 {
+            return Err(ParseError::UnsupportedAbi(data[7]));
+        }
+        Ok(Self { class, encoding })
     }
 }
 
@@ -153,37 +287,41 @@ impl ElfType {
 }
 
 impl Segment {
-    pub fn parse(raw: &RawSegment) -> Option<Self> {
-        let ty = SegmentType::parse(raw.ty)?;
+    pub fn parse(raw: &RawSegment) -> Result<Self, ParseError> {
+        let ty = SegmentType::parse(raw.ty);
         let flags = SegmentFlags::parse(raw.flags);
         let offset = raw.offset;
-        let vaddr = VirtAddr::new(raw.vaddr as usize)?;
+        let vaddr =
+            VirtAddr::new(raw.vaddr as usize).ok_or(ParseError::NonCanonicalSegment(raw.vaddr))?;
         let file_size = raw.file_size as usize;
         let mem_size = MemorySize::new(raw.mem_size as usize);
         let alignment = raw.alignment as usize;
-        if *vaddr % alignment != 0 {
-            return None;
+        // Segments may start mid-page, as long as they are at the same place in the file
+        if alignment > 1 && *vaddr % alignment != offset as usize % alignment {
+            return Err(ParseError::MisalignedSegment(raw.vaddr));
         }
-        Some(Self {
+        Ok(Self {
             ty,
             flags,
             offset,
             vaddr,
             file_size,
             mem_size,
+            alignment,
         })
     }
 }
 
 impl SegmentType {
-    pub fn parse(ty: Word) -> Option<Self> {
+    pub fn parse(ty: Word) -> Self {
         match ty {
-            0 => Some(Self::Null),
-            1 => Some(Self::Load),
-            2 => Some(Self::Dynamic),
-            3 => Some(Self::Interpreter),
-            4 => Some(Self::Note),
-            _ => None,
+            0 => Self::Null,
+            1 => Self::Load,
+            2 => Self::Dynamic,
+            3 => Self::Interpreter,
+            4 => Self::Note,
+            7 => Self::Tls,
+            ty => Self::Other(ty),
         }
     }
 }
@@ -191,9 +329,9 @@ impl SegmentType {
 impl SegmentFlags {
     pub fn parse(flags: Word) -> Self {
         Self {
-            read: flags & 1 != 0,
+            read: flags & 4 != 0,
             write: flags & 2 != 0,
-            exec: flags & 4 != 0,
+            exec: flags & 1 != 0,
         }
     }
 }
@@ -206,15 +344,24 @@ impl<'a> ProgramHeader<'a> {
             len: entry_count,
         }
     }
+    pub fn get(&self, index: usize) -> Option<Segment> {
+        Segment::parse(self.raw(index)?).ok()
+    }
+    fn raw(&self, index: usize) -> Option<&'a RawSegment> {
+        if index >= self.len {
+            return None;
+        }
+        let offset = index.checked_mul(self.entry_size)?;
+        RawSegment::from(self.raw.get(offset..offset.checked_add(self.entry_size)?)?)
+    }
 }
 
 impl<'a> Index<usize> for ProgramHeader<'a> {
     type Output = RawSegment;
 
     fn index(&self, index: usize) -> &Self::Output {
-        assert!(index < self.len);
-        let offset = index * self.entry_size;
-        RawSegment::from(&self.raw[offset..offset + self.entry_size]).expect("Invalid segment")
+        self.raw(index)
+            .expect("Segment index out of bounds, or table not from Elf::parse")
     }
 }
 
@@ -234,12 +381,50 @@ impl<'a, 'b> Iterator for SegmentIter<'a, 'b> {
     type Item = Segment;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.program_header.len {
-            let segment = Segment::parse(&self.program_header[self.index]);
-            self.index += 1;
-            segment
-        } else {
-            None
-        }
+        let segment = self.program_header.get(self.index)?;
+        self.index += 1;
+        Some(segment)
+    }
+}
+
+impl<'a> NoteIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
     }
 }
+
+impl<'a> Iterator for NoteIter<'a> {
+    type Item = Note<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        const HEADER_SIZE: usize = 3 * mem::size_of::<Word>();
+        let data = mem::take(&mut self.data);
+        let word = |index: usize| {
+            let bytes = data.get(index * 4..index * 4 + 4)?;
+            Some(Word::from_ne_bytes(bytes.try_into().ok()?))
+        };
+        let name_size = word(0)? as usize;
+        let desc_size = word(1)? as usize;
+        let ty = word(2)?;
+
+        let desc_start = HEADER_SIZE.checked_add(name_size.next_multiple_of(4))?;
+        let desc_end = desc_start.checked_add(desc_size)?;
+        let name = data.get(HEADER_SIZE..HEADER_SIZE + name_size)?;
+        let name = name.strip_suffix(b"\0").unwrap_or(name);
+        let desc = data.get(desc_start..desc_end)?;
+
+        self.data = data.get(desc_end.next_multiple_of(4)..).unwrap_or_default();
+        Some(Note { name, ty, desc })
+    }
+}
+
+/// `count` entries of `entry_size` bytes at `offset`, None if not in `data` or misaligned for `T`
+fn table<T>(data: &[u8], offset: Offset, entry_size: Half, count: Half) -> Option<&[u8]> {
+    let offset = usize::try_from(offset).ok()?;
+    let size = entry_size as usize * count as usize;
+    let table = data.get(offset..offset.checked_add(size)?)?;
+    table
+        .as_ptr()
+        .is_aligned_to(mem::align_of::<T>())
+        .then_some(table)
+}
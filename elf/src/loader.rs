@@ -0,0 +1,190 @@
+//! Page granular layout of the PT_LOAD segments of an [Elf]
+
+#[cfg(test)]
+mod test;
+
+use crate::Elf;
+use crate::Segment;
+use crate::SegmentFlags;
+use crate::SegmentType;
+use x64::mem::MemorySize;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::VirtAddr;
+use x64::mem::page::Page;
+use x64::mem::page::size::Page4KiB;
+
+pub const PAGE_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// The segment at this address is both writable and executable
+    WritableExecutable(VirtAddr),
+    /// A writable and an executable segment share the page at this address
+    SharedWritableExecutable(VirtAddr),
+    /// The segment at this address has more bytes in the file than in memory
+    FileSizeTooLarge(VirtAddr),
+    /// The contents of the segment at this address are past the end of the file
+    OutOfFile(VirtAddr),
+    /// The segment at this address runs past the end of the address space
+    AddressOverflow(VirtAddr),
+    /// The segment at this address is not sorted by address, or overlaps the previous one
+    Overlapping(VirtAddr),
+}
+
+/// A page of the loaded image, with the permissions of every segment in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageMapping {
+    pub page: Page<Page4KiB>,
+    pub flags: SegmentFlags,
+}
+
+/// Lays out the PT_LOAD segments of an [Elf] in pages
+///
+/// Segments may start mid-page and share pages with their neighbours, every page is
+/// yielded once by [Loader::pages] with the permissions of all segments in it merged.
+/// Nothing is ever writable and executable.
+pub struct Loader<'a, 'b> {
+    elf: &'b Elf<'a>,
+}
+
+impl<'a, 'b> Loader<'a, 'b> {
+    /// Checks that the segments of `elf` can be loaded
+    pub fn new(elf: &'b Elf<'a>) -> Result<Self, LoadError> {
+        let loader = Self { elf };
+        let mut previous_end = 0;
+        // Last page seen, with the permissions merged so far
+        let mut shared: Option<(usize, SegmentFlags)> = None;
+        for segment in loader.segments() {
+            let vaddr = segment.vaddr;
+            if segment.flags.write && segment.flags.exec {
+                return Err(LoadError::WritableExecutable(vaddr));
+            }
+            if segment.file_size > *segment.mem_size {
+                return Err(LoadError::FileSizeTooLarge(vaddr));
+            }
+            if elf.segment_data(&segment).is_none() {
+                return Err(LoadError::OutOfFile(vaddr));
+            }
+            let last = vaddr
+                .as_usize()
+                .checked_add(*segment.mem_size - 1)
+                .and_then(VirtAddr::new)
+                .ok_or(LoadError::AddressOverflow(vaddr))?;
+            if vaddr.as_usize() < previous_end {
+                return Err(LoadError::Overlapping(vaddr));
+            }
+            previous_end = last.as_usize() + 1;
+
+            let (first_page, last_page) = page_span(&segment);
+            let mut flags = segment.flags;
+            if let Some((page, shared_flags)) = shared {
+                if page == first_page {
+                    flags = merge(shared_flags, flags);
+                    if flags.write && flags.exec {
+                        return Err(LoadError::SharedWritableExecutable(VirtAddr::new_panic(
+                            page,
+                        )));
+                    }
+                }
+            }
+            shared = Some((
+                last_page,
+                if first_page == last_page {
+                    flags
+                } else {
+                    segment.flags
+                },
+            ));
+        }
+        Ok(loader)
+    }
+}
+
+impl<'a, 'b> Loader<'a, 'b> {
+    /// PT_LOAD segments taking memory
+    pub fn segments(&self) -> impl Iterator<Item = Segment> + 'b {
+        self.elf
+            .program_header
+            .into_iter()
+            .filter(|segment| segment.ty == SegmentType::Load && *segment.mem_size != 0)
+    }
+    /// From the start of the first page of the image to the end of the last one,
+    /// null if no segment takes memory
+    pub fn span(&self) -> VirtualMemoryRegion {
+        let mut spans = self.segments().map(|segment| page_span(&segment));
+        let Some((first, mut last)) = spans.next() else {
+            return VirtualMemoryRegion::null();
+        };
+        if let Some((_, end)) = spans.last() {
+            last = end;
+        }
+        VirtualMemoryRegion::new(
+            VirtAddr::new_panic(first),
+            MemorySize::new(last - first + PAGE_SIZE),
+        )
+    }
+    /// Every page of the image once, in ascending order
+    pub fn pages(&self) -> impl Iterator<Item = PageMapping> + '_ {
+        let mut previous = None;
+        self.segments()
+            .flat_map(|segment| {
+                let (first, last) = page_span(&segment);
+                (first..=last).step_by(PAGE_SIZE)
+            })
+            .filter(move |&page| previous.replace(page) != Some(page))
+            .map(|page| PageMapping {
+                page: Page::containing(VirtAddr::new_panic(page)),
+                flags: self.flags(page),
+            })
+    }
+    /// Writes the contents of `page` to `dst`, bytes not backed by the file are zeroed
+    pub fn fill(&self, page: Page<Page4KiB>, dst: &mut [u8; PAGE_SIZE]) {
+        let page = page.boundary().as_usize();
+        dst.fill(0);
+        for segment in self.segments() {
+            let data = self
+                .elf
+                .segment_data(&segment)
+                .expect("Segment was checked to be in the file");
+            let start = segment.vaddr.as_usize();
+            // Offsets in `data` and in `dst` of the first byte to copy
+            let (from, to) = if start >= page {
+                (0, start - page)
+            } else {
+                (page - start, 0)
+            };
+            if from >= data.len() || to >= PAGE_SIZE {
+                continue;
+            }
+            let len = (data.len() - from).min(PAGE_SIZE - to);
+            dst[to..to + len].copy_from_slice(&data[from..from + len]);
+        }
+    }
+    /// Permissions of every segment in the page starting at `page`
+    fn flags(&self, page: usize) -> SegmentFlags {
+        self.segments()
+            .filter(|segment| {
+                let (first, last) = page_span(segment);
+                (first..=last).contains(&page)
+            })
+            .fold(SegmentFlags::parse(0), |flags, segment| {
+                merge(flags, segment.flags)
+            })
+    }
+}
+
+/// Starts of the first and last pages of a segment taking memory
+fn page_span(segment: &Segment) -> (usize, usize) {
+    let start = segment.vaddr.as_usize();
+    let last = start + (*segment.mem_size - 1);
+    (start & !(PAGE_SIZE - 1), last & !(PAGE_SIZE - 1))
+}
+
+fn merge(a: SegmentFlags, b: SegmentFlags) -> SegmentFlags {
+    SegmentFlags {
+        read: a.read || b.read,
+        write: a.write || b.write,
+        exec: a.exec || b.exec,
+    }
+}
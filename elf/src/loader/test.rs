@@ -0,0 +1,194 @@
+use super::LoadError;
+use super::Loader;
+use super::PAGE_SIZE;
+use super::PageMapping;
+use crate::Elf;
+use crate::SegmentFlags;
+use crate::test::Aligned;
+use crate::test::OMAGIC;
+use crate::test::PACKED;
+use crate::test::SPLIT;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use x64::mem::MemorySize;
+use x64::mem::VirtualMemoryRegion;
+use x64::mem::addr::Address;
+use x64::mem::addr::VirtAddr;
+use x64::mem::page::Page;
+
+const R: u32 = 4;
+const W: u32 = 2;
+const X: u32 = 1;
+
+/// Builds an executable with `(flags, offset, vaddr, file_size, mem_size)` PT_LOAD segments,
+/// the file is 4 pages of `0xAA` after the headers
+fn image(segments: &[(u32, u64, u64, u64, u64)]) -> Box<Aligned<[u8; 5 * PAGE_SIZE]>> {
+    let mut image = Box::new(Aligned([0xAA; 5 * PAGE_SIZE]));
+    let mut header = Vec::new();
+    header.extend_from_slice(b"\x7FELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    header.extend_from_slice(&2u16.to_ne_bytes());
+    header.extend_from_slice(&0x3Eu16.to_ne_bytes());
+    header.extend_from_slice(&1u32.to_ne_bytes());
+    header.extend_from_slice(&0x1000u64.to_ne_bytes());
+    header.extend_from_slice(&64u64.to_ne_bytes());
+    header.extend_from_slice(&0u64.to_ne_bytes());
+    header.extend_from_slice(&0u32.to_ne_bytes());
+    for half in [64, 56, segments.len() as u16, 64, 0, 0] {
+        header.extend_from_slice(&half.to_ne_bytes());
+    }
+    for &(flags, offset, vaddr, file_size, mem_size) in segments {
+        // ELF flags are X = 1, W = 2, R = 4
+        header.extend_from_slice(&1u32.to_ne_bytes());
+        header.extend_from_slice(&flags.to_ne_bytes());
+        for xword in [offset, vaddr, vaddr, file_size, mem_size, 0x1000] {
+            header.extend_from_slice(&xword.to_ne_bytes());
+        }
+    }
+    image.0[..PAGE_SIZE].fill(0);
+    image.0[..header.len()].copy_from_slice(&header);
+    image
+}
+
+fn mapping(page: usize, flags: u32) -> PageMapping {
+    PageMapping {
+        page: Page::containing(VirtAddr::new_panic(page)),
+        flags: SegmentFlags::parse(flags),
+    }
+}
+
+fn fill(loader: &Loader, page: usize) -> Box<[u8; PAGE_SIZE]> {
+    let mut dst = Box::new([0x55; PAGE_SIZE]);
+    loader.fill(Page::containing(VirtAddr::new_panic(page)), &mut dst);
+    dst
+}
+
+#[test]
+fn test_split_fixture() {
+    let elf = Elf::parse(&SPLIT.0).unwrap();
+    let loader = Loader::new(&elf).unwrap();
+    assert_eq!(
+        loader.pages().collect::<Vec<_>>(),
+        [
+            mapping(0x400000, R),
+            mapping(0x401000, R | X),
+            mapping(0x402000, R),
+            mapping(0x403000, R | W),
+            mapping(0x404000, R | W),
+            mapping(0x405000, R | W),
+        ]
+    );
+
+    assert_eq!(
+        loader.span(),
+        VirtualMemoryRegion::new(VirtAddr::new_panic(0x400000), MemorySize::new(0x6000))
+    );
+
+    let text = fill(&loader, 0x401000);
+    assert_eq!(text[..0x55], SPLIT.0[0x1000..0x1055]);
+    assert!(text[0x55..].iter().all(|&byte| byte == 0));
+
+    let data = fill(&loader, 0x403000);
+    assert_eq!(data[0x18..0x1C], 42i32.to_le_bytes());
+    assert!(data[..0x18].iter().all(|&byte| byte == 0));
+    assert!(data[0x1C..].iter().all(|&byte| byte == 0));
+
+    assert!(fill(&loader, 0x405000).iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn test_packed_fixture() {
+    let elf = Elf::parse(&PACKED.0).unwrap();
+    let loader = Loader::new(&elf).unwrap();
+    assert_eq!(
+        loader.pages().collect::<Vec<_>>(),
+        [
+            mapping(0x400000, R | X),
+            mapping(0x401000, R | W),
+            mapping(0x402000, R | W),
+            mapping(0x403000, R | W),
+        ]
+    );
+
+    // The data segment starts mid-page, the text before it in the file must not leak in
+    let data = fill(&loader, 0x401000);
+    assert_eq!(data[0x168..0x16C], 42i32.to_le_bytes());
+    assert!(data[..0x168].iter().all(|&byte| byte == 0));
+    assert!(data[0x16C..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn test_writable_executable() {
+    let elf = Elf::parse(&OMAGIC.0).unwrap();
+    assert_eq!(
+        Loader::new(&elf).err(),
+        Some(LoadError::WritableExecutable(VirtAddr::new_panic(0x4000B0)))
+    );
+}
+
+#[test]
+fn test_shared_page() {
+    let raw = image(&[
+        (R, 0x1000, 0x10000, 0x10, 0x10),
+        (R | W, 0x2800, 0x10800, 0x10, 0x1000),
+    ]);
+    let elf = Elf::parse(&raw.0).unwrap();
+    let loader = Loader::new(&elf).unwrap();
+    assert_eq!(
+        loader.pages().collect::<Vec<_>>(),
+        [mapping(0x10000, R | W), mapping(0x11000, R | W)]
+    );
+
+    let shared = fill(&loader, 0x10000);
+    assert!(shared[..0x10].iter().all(|&byte| byte == 0xAA));
+    assert!(shared[0x10..0x800].iter().all(|&byte| byte == 0));
+    assert!(shared[0x800..0x810].iter().all(|&byte| byte == 0xAA));
+    assert!(shared[0x810..].iter().all(|&byte| byte == 0));
+    assert!(fill(&loader, 0x11000).iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn test_shared_writable_executable() {
+    let raw = image(&[
+        (R | X, 0x1000, 0x10000, 0x100, 0x100),
+        (R, 0x1100, 0x10100, 0x100, 0x100),
+        (R | W, 0x2800, 0x10800, 0x10, 0x10),
+    ]);
+    let elf = Elf::parse(&raw.0).unwrap();
+    assert_eq!(
+        Loader::new(&elf).err(),
+        Some(LoadError::SharedWritableExecutable(VirtAddr::new_panic(
+            0x10000
+        )))
+    );
+}
+
+#[test]
+fn test_invalid_segments() {
+    let check = |segments: &[(u32, u64, u64, u64, u64)]| {
+        let raw = image(segments);
+        let elf = Elf::parse(&raw.0).unwrap();
+        Loader::new(&elf).err()
+    };
+    let at = |addr| VirtAddr::new_panic(addr);
+    assert_eq!(
+        check(&[(R, 0x1000, 0x10000, 0x20, 0x10)]),
+        Some(LoadError::FileSizeTooLarge(at(0x10000)))
+    );
+    assert_eq!(
+        check(&[(R, 0x4000, 0x10000, 0x2000, 0x2000)]),
+        Some(LoadError::OutOfFile(at(0x10000)))
+    );
+    assert_eq!(
+        check(&[
+            (R, 0x1000, 0x10000, 0x100, 0x100),
+            (R | W, 0x1080, 0x10080, 0x100, 0x100),
+        ]),
+        Some(LoadError::Overlapping(at(0x10080)))
+    );
+    assert_eq!(
+        check(&[(R, 0x1000, 0x7FFF_FFFF_F000, 0x100, 0x2000)]),
+        Some(LoadError::AddressOverflow(at(0x7FFF_FFFF_F000)))
+    );
+    // Segments without memory are ignored
+    assert_eq!(check(&[(R | W | X, 0x1000, 0x10000, 0, 0)]), None);
+}
@@ -0,0 +1,400 @@
+//! Relocations of position independent executables and shared objects, see [Elf::relocations]
+
+#[cfg(test)]
+mod test;
+
+use crate::Elf;
+use crate::SegmentType;
+use crate::dynamic::DynamicIter;
+use crate::dynamic::DynamicTag;
+use crate::headers::RawSymbol;
+use crate::section::StringTable;
+use crate::symbol::SHN_ABS;
+use crate::symbol::SymbolBinding;
+use crate::symbol::SymbolTable;
+use crate::types::SXWord;
+use crate::types::XWord;
+use core::mem;
+use x64::mem::addr::Address;
+
+pub const R_X86_64_NONE: u32 = 0;
+pub const R_X86_64_64: u32 = 1;
+pub const R_X86_64_PC32: u32 = 2;
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+pub const R_X86_64_RELATIVE: u32 = 8;
+pub const R_X86_64_32: u32 = 10;
+pub const R_X86_64_32S: u32 = 11;
+pub const R_X86_64_PC64: u32 = 24;
+
+const REL_SIZE: usize = 2 * mem::size_of::<XWord>();
+const RELA_SIZE: usize = 3 * mem::size_of::<XWord>();
+
+/// Entry of a RELA table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rela {
+    /// Link time address of the place to relocate
+    pub offset: XWord,
+    pub ty: u32,
+    pub symbol: u32,
+    pub addend: SXWord,
+}
+
+/// Entry of a REL table, the addend is read from the place to relocate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rel {
+    /// Link time address of the place to relocate
+    pub offset: XWord,
+    pub ty: u32,
+    pub symbol: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocError {
+    /// The dynamic section, a relocation table or a symbol is not in the file
+    Malformed,
+    /// Only the relocation types of [Relocations::apply] are supported
+    Unsupported(u32),
+    /// The place to relocate, at this link time address, is outside the image
+    OutOfImage(XWord),
+    /// The symbol at this index of the dynamic symbol table is neither defined nor weak
+    UndefinedSymbol(u32),
+    /// The value does not fit the place to relocate at this link time address
+    Overflow(XWord),
+}
+
+/// Entries of a RELA table, stops at the first truncated one
+#[derive(Clone)]
+pub struct RelaIter<'a> {
+    data: &'a [u8],
+}
+
+/// Entries of a REL table, stops at the first truncated one
+#[derive(Clone)]
+pub struct RelIter<'a> {
+    data: &'a [u8],
+}
+
+/// Relocation tables found through the dynamic section
+#[derive(Clone)]
+pub struct Relocations<'a> {
+    pub rela: RelaIter<'a>,
+    pub rel: RelIter<'a>,
+    pub plt: PltRelocations<'a>,
+    /// Symbols the entries refer to, None without a DT_SYMTAB entry
+    pub symbols: Option<SymbolTable<'a>>,
+}
+
+/// Relocations of the PLT, in the format given by [DynamicTag::PltRel]
+#[derive(Clone)]
+pub enum PltRelocations<'a> {
+    Rela(RelaIter<'a>),
+    Rel(RelIter<'a>),
+}
+
+/// Memory being relocated, see [Relocations::apply]
+struct Image<'a, 'b> {
+    data: &'b mut [u8],
+    base: usize,
+    bias: usize,
+    symbols: Option<SymbolTable<'a>>,
+}
+
+impl<'a> Elf<'a> {
+    /// Relocation tables of the PT_DYNAMIC segment, all empty without one
+    pub fn relocations(&self) -> Result<Relocations<'a>, RelocError> {
+        let mut relocations = Relocations {
+            rela: RelaIter::new(&[]),
+            rel: RelIter::new(&[]),
+            plt: PltRelocations::Rela(RelaIter::new(&[])),
+            symbols: None,
+        };
+        let Some(dynamic) = self
+            .program_header
+            .into_iter()
+            .find(|segment| segment.ty == SegmentType::Dynamic)
+        else {
+            return Ok(relocations);
+        };
+        let dynamic = DynamicIter::new(self.segment_data(&dynamic).ok_or(RelocError::Malformed)?);
+
+        relocations.rela = RelaIter::new(self.relocation_table(
+            &dynamic,
+            DynamicTag::Rela,
+            DynamicTag::RelaSize,
+            Some(DynamicTag::RelaEntry),
+            RELA_SIZE,
+        )?);
+        relocations.rel = RelIter::new(self.relocation_table(
+            &dynamic,
+            DynamicTag::Rel,
+            DynamicTag::RelSize,
+            Some(DynamicTag::RelEntry),
+            REL_SIZE,
+        )?);
+        // The entry size of the PLT relocations is implied by their format
+        let (address, size) = (DynamicTag::JmpRel, DynamicTag::PltRelSize);
+        relocations.plt = match dynamic.value(DynamicTag::PltRel) {
+            None => relocations.plt,
+            Some(format) => match DynamicTag::parse(format as SXWord) {
+                DynamicTag::Rela => PltRelocations::Rela(RelaIter::new(
+                    self.relocation_table(&dynamic, address, size, None, RELA_SIZE)?,
+                )),
+                DynamicTag::Rel => PltRelocations::Rel(RelIter::new(
+                    self.relocation_table(&dynamic, address, size, None, REL_SIZE)?,
+                )),
+                _ => return Err(RelocError::Malformed),
+            },
+        };
+        relocations.symbols = self.dynamic_symbol_table(&dynamic)?;
+        Ok(relocations)
+    }
+    /// Contents of the table at the value of `address`, of the value of `size` bytes, empty
+    /// if the address is not in `dynamic`. The value of `entry`, if any, must be `entry_size`
+    fn relocation_table(
+        &self,
+        dynamic: &DynamicIter<'a>,
+        address: DynamicTag,
+        size: DynamicTag,
+        entry: Option<DynamicTag>,
+        entry_size: usize,
+    ) -> Result<&'a [u8], RelocError> {
+        let Some(address) = dynamic.value(address) else {
+            return Ok(&[]);
+        };
+        if entry
+            .and_then(|entry| dynamic.value(entry))
+            .is_some_and(|size| size as usize != entry_size)
+        {
+            return Err(RelocError::Malformed);
+        }
+        let size = dynamic.value(size).unwrap_or(0) as usize;
+        self.vaddr_data(address as usize, size)
+            .ok_or(RelocError::Malformed)
+    }
+    /// Symbol table at DT_SYMTAB with names from DT_STRTAB, so that section headers are not
+    /// needed. It has as many entries as the DT_HASH table has chains, or else runs to the
+    /// end of its segment
+    fn dynamic_symbol_table(
+        &self,
+        dynamic: &DynamicIter<'a>,
+    ) -> Result<Option<SymbolTable<'a>>, RelocError> {
+        let Some(address) = dynamic.value(DynamicTag::SymTab) else {
+            return Ok(None);
+        };
+        let entry_size = dynamic
+            .value(DynamicTag::SymEntry)
+            .map_or(mem::size_of::<RawSymbol>(), |size| size as usize);
+        let data = match dynamic.value(DynamicTag::Hash) {
+            Some(hash) => {
+                // The bucket count comes before the chain count
+                let chains = (hash as usize)
+                    .checked_add(4)
+                    .and_then(|chains| self.vaddr_data(chains, 4))
+                    .ok_or(RelocError::Malformed)?;
+                let count = u32::from_ne_bytes(chains.try_into().unwrap()) as usize;
+                let size = count.checked_mul(entry_size).ok_or(RelocError::Malformed)?;
+                self.vaddr_data(address as usize, size)
+            }
+            None => self.vaddr_tail(address as usize),
+        };
+        let strings = dynamic
+            .value(DynamicTag::StrTab)
+            .zip(dynamic.value(DynamicTag::StrSize))
+            .and_then(|(address, size)| self.vaddr_data(address as usize, size as usize));
+        data.zip(strings)
+            .and_then(|(data, strings)| {
+                SymbolTable::from_raw(data, entry_size, StringTable::new(strings))
+            })
+            .map(Some)
+            .ok_or(RelocError::Malformed)
+    }
+    /// Contents in the file of the `size` bytes at link time address `vaddr`
+    fn vaddr_data(&self, vaddr: usize, size: usize) -> Option<&'a [u8]> {
+        let end = vaddr.checked_add(size)?;
+        self.program_header
+            .into_iter()
+            .filter(|segment| segment.ty == SegmentType::Load)
+            .find(|segment| {
+                let start = segment.vaddr.as_usize();
+                start <= vaddr && end <= start + segment.file_size
+            })
+            .and_then(|segment| {
+                let data = self.segment_data(&segment)?;
+                let start = vaddr - segment.vaddr.as_usize();
+                data.get(start..start + size)
+            })
+    }
+    /// Contents in the file from link time address `vaddr` to the end of its segment
+    fn vaddr_tail(&self, vaddr: usize) -> Option<&'a [u8]> {
+        self.program_header
+            .into_iter()
+            .filter(|segment| segment.ty == SegmentType::Load)
+            .find(|segment| {
+                let start = segment.vaddr.as_usize();
+                start <= vaddr && vaddr < start + segment.file_size
+            })
+            .and_then(|segment| {
+                let data = self.segment_data(&segment)?;
+                data.get(vaddr - segment.vaddr.as_usize()..)
+            })
+    }
+}
+
+impl Relocations<'_> {
+    /// Applies every relocation to `image`, which holds the memory at link time address
+    /// `base`, for the file to run `bias` bytes above where it was linked
+    ///
+    /// With S the runtime address of the symbol, A the addend, B the bias and P the runtime
+    /// address of the place, the supported types are NONE, 64 and 32 (S + A, 32 zero
+    /// extended), 32S (S + A sign extended), PC32 and PC64 (S + A - P), GLOB_DAT and
+    /// JUMP_SLOT (S), and RELATIVE (B + A).
+    /// Symbols are looked up in the file only, undefined weak ones are 0.
+    pub fn apply(self, image: &mut [u8], base: usize, bias: usize) -> Result<(), RelocError> {
+        let mut image = Image {
+            data: image,
+            base,
+            bias,
+            symbols: self.symbols,
+        };
+        for rela in self.rela {
+            image.apply_rela(rela)?;
+        }
+        for rel in self.rel {
+            image.apply_rel(rel)?;
+        }
+        match self.plt {
+            PltRelocations::Rela(mut plt) => plt.try_for_each(|rela| image.apply_rela(rela)),
+            PltRelocations::Rel(mut plt) => plt.try_for_each(|rel| image.apply_rel(rel)),
+        }
+    }
+}
+
+impl<'a> RelaIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> RelIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl Iterator for RelaIter<'_> {
+    type Item = Rela;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry, rest) = self.data.split_at_checked(RELA_SIZE)?;
+        self.data = rest;
+        let (offset, ty, symbol) = parse_entry(entry);
+        Some(Rela {
+            offset,
+            ty,
+            symbol,
+            addend: xword(entry, 2) as SXWord,
+        })
+    }
+}
+
+impl Iterator for RelIter<'_> {
+    type Item = Rel;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (entry, rest) = self.data.split_at_checked(REL_SIZE)?;
+        self.data = rest;
+        let (offset, ty, symbol) = parse_entry(entry);
+        Some(Rel { offset, ty, symbol })
+    }
+}
+
+/// Offset, type and symbol of a REL or RELA entry
+fn parse_entry(entry: &[u8]) -> (XWord, u32, u32) {
+    let info = xword(entry, 1);
+    (xword(entry, 0), info as u32, (info >> 32) as u32)
+}
+
+fn xword(entry: &[u8], index: usize) -> XWord {
+    let bytes = &entry[index * 8..index * 8 + 8];
+    XWord::from_ne_bytes(bytes.try_into().unwrap())
+}
+
+impl Image<'_, '_> {
+    fn apply_rela(&mut self, rela: Rela) -> Result<(), RelocError> {
+        self.apply(rela.offset, rela.ty, rela.symbol, Some(rela.addend))
+    }
+    fn apply_rel(&mut self, rel: Rel) -> Result<(), RelocError> {
+        self.apply(rel.offset, rel.ty, rel.symbol, None)
+    }
+    /// Relocates the place at link time address `offset`, reading the addend from it
+    /// if there is none
+    fn apply(
+        &mut self,
+        offset: XWord,
+        ty: u32,
+        symbol: u32,
+        addend: Option<SXWord>,
+    ) -> Result<(), RelocError> {
+        let size = match ty {
+            R_X86_64_NONE => return Ok(()),
+            R_X86_64_64 | R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT | R_X86_64_RELATIVE
+            | R_X86_64_PC64 => 8,
+            R_X86_64_PC32 | R_X86_64_32 | R_X86_64_32S => 4,
+            ty => return Err(RelocError::Unsupported(ty)),
+        };
+        let s = if ty == R_X86_64_RELATIVE {
+            0
+        } else {
+            self.symbol(symbol)?
+        };
+        let b = self.bias as SXWord;
+        let p = self.bias.wrapping_add(offset as usize) as SXWord;
+        let place = (offset as usize)
+            .checked_sub(self.base)
+            .and_then(|start| self.data.get_mut(start..start.checked_add(size)?))
+            .ok_or(RelocError::OutOfImage(offset))?;
+        let a = addend.unwrap_or_else(|| match ty {
+            _ if size == 8 => SXWord::from_ne_bytes(place.try_into().unwrap()),
+            R_X86_64_32 => u32::from_ne_bytes(place.try_into().unwrap()).into(),
+            _ => i32::from_ne_bytes(place.try_into().unwrap()).into(),
+        });
+
+        let value = match ty {
+            R_X86_64_64 | R_X86_64_32 | R_X86_64_32S => s.wrapping_add(a),
+            R_X86_64_PC32 | R_X86_64_PC64 => s.wrapping_add(a).wrapping_sub(p),
+            R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => s,
+            R_X86_64_RELATIVE => b.wrapping_add(a),
+            _ => unreachable!("Relocation type was checked above"),
+        };
+        let overflow = |_| RelocError::Overflow(offset);
+        match ty {
+            _ if size == 8 => place.copy_from_slice(&value.to_ne_bytes()),
+            R_X86_64_32 => {
+                place.copy_from_slice(&u32::try_from(value).map_err(overflow)?.to_ne_bytes())
+            }
+            _ => place.copy_from_slice(&i32::try_from(value).map_err(overflow)?.to_ne_bytes()),
+        }
+        Ok(())
+    }
+    /// Runtime address of the symbol at `index` of the dynamic symbol table
+    fn symbol(&self, index: u32) -> Result<SXWord, RelocError> {
+        // Index 0 is the undefined symbol, used by relocations without one
+        if index == 0 {
+            return Ok(0);
+        }
+        let symbol = self
+            .symbols
+            .and_then(|symbols| symbols.get(index as usize))
+            .ok_or(RelocError::Malformed)?;
+        if symbol.section == SHN_ABS {
+            Ok(symbol.value as SXWord)
+        } else if symbol.is_defined() {
+            Ok(self.bias.wrapping_add(symbol.value) as SXWord)
+        } else if symbol.binding == SymbolBinding::Weak {
+            Ok(0)
+        } else {
+            Err(RelocError::UndefinedSymbol(index))
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use super::PltRelocations;
+use super::R_X86_64_32;
+use super::R_X86_64_32S;
+use super::R_X86_64_GLOB_DAT;
+use super::R_X86_64_JUMP_SLOT;
+use super::R_X86_64_NONE;
+use super::R_X86_64_PC32;
+use super::R_X86_64_RELATIVE;
+use super::RelIter;
+use super::Rela;
+use super::RelaIter;
+use super::RelocError;
+use super::Relocations;
+use crate::Elf;
+use crate::SegmentType;
+use crate::dynamic::DynamicTag;
+use crate::loader::Loader;
+use crate::loader::PAGE_SIZE;
+use crate::test::Aligned;
+use crate::test::PIE;
+use crate::test::REL;
+use crate::test::SHARED;
+use crate::test::symbol;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use x64::mem::addr::Address;
+
+const BIAS: usize = 0x4000_0000;
+
+fn rela(offset: u64, ty: u32, addend: i64) -> Rela {
+    Rela {
+        offset,
+        ty,
+        symbol: 0,
+        addend,
+    }
+}
+
+/// Applies `relocations`, which may not refer to symbols
+fn relocate(
+    image: &mut [u8],
+    base: usize,
+    bias: usize,
+    relocations: impl IntoIterator<Item = Rela>,
+) -> Result<(), RelocError> {
+    let mut raw = Vec::new();
+    for rela in relocations {
+        let info = rela.ty as u64 | (rela.symbol as u64) << 32;
+        for xword in [rela.offset, info, rela.addend as u64] {
+            raw.extend_from_slice(&xword.to_ne_bytes());
+        }
+    }
+    let relocations = Relocations {
+        rela: RelaIter::new(&raw),
+        rel: RelIter::new(&[]),
+        plt: PltRelocations::Rela(RelaIter::new(&[])),
+        symbols: None,
+    };
+    relocations.apply(image, base, bias)
+}
+
+/// Loads `elf` like a loader would, relocated `bias` bytes above where it was linked at 0
+fn load(elf: &Elf, bias: usize) -> Result<Vec<u8>, RelocError> {
+    let loader = Loader::new(elf).unwrap();
+    let span = loader.span();
+    assert_eq!(span.start().as_usize(), 0);
+    let mut image = vec![0; *span.size()];
+    for mapping in loader.pages() {
+        let offset = mapping.page.boundary().as_usize();
+        let dst = (&mut image[offset..offset + PAGE_SIZE]).try_into().unwrap();
+        loader.fill(mapping.page, dst);
+    }
+    elf.relocations()?.apply(&mut image, 0, bias)?;
+    Ok(image)
+}
+
+fn read(image: &[u8], addr: usize) -> usize {
+    usize::from_ne_bytes(image[addr..addr + 8].try_into().unwrap())
+}
+
+/// Checks the places relocated in `image`, loaded from [SHARED] or [REL] at [BIAS]
+fn assert_shared(elf: &Elf, image: &[u8]) {
+    let counter = BIAS + symbol(elf, "counter");
+    assert_eq!(read(image, symbol(elf, "counter_ptr")), counter);
+    assert_eq!(
+        read(image, symbol(elf, "message_ptr")),
+        BIAS + symbol(elf, "message")
+    );
+    // Undefined weak symbols are null
+    assert_eq!(read(image, symbol(elf, "missing_ptr")), 0);
+
+    let got = elf.section_by_name(".got").unwrap().addr;
+    assert_eq!(read(image, got), counter);
+    // The first 3 entries are reserved for a lazy binder
+    let got_plt = elf.section_by_name(".got.plt").unwrap().addr;
+    assert_eq!(
+        read(image, got_plt + 3 * 8),
+        BIAS + symbol(elf, "get_counter")
+    );
+    assert_eq!(read(image, got_plt + 4 * 8), BIAS + symbol(elf, "call"));
+}
+
+#[test]
+fn test_rela_iter() {
+    let mut raw = Vec::new();
+    for xword in [0x1008u64, 8 | 3 << 32, 0x1234, 0x1010, 0, -8i64 as u64] {
+        raw.extend_from_slice(&xword.to_ne_bytes());
+    }
+    raw.extend_from_slice(&[0; 8]);
+    let relocations: Vec<Rela> = RelaIter::new(&raw).collect();
+    assert_eq!(
+        relocations,
+        [
+            Rela {
+                offset: 0x1008,
+                ty: R_X86_64_RELATIVE,
+                symbol: 3,
+                addend: 0x1234,
+            },
+            rela(0x1010, R_X86_64_NONE, -8),
+        ]
+    );
+}
+
+#[test]
+fn test_relocate() {
+    let mut image = [0u8; 0x20];
+    let relocations = [
+        rela(0x1000, R_X86_64_RELATIVE, 0x1018),
+        rela(0x1008, R_X86_64_NONE, 0),
+        rela(0x1010, R_X86_64_RELATIVE, -0x10),
+    ];
+    relocate(&mut image, 0x1000, 0x200000, relocations).unwrap();
+    assert_eq!(image[..8], 0x201018u64.to_ne_bytes());
+    assert_eq!(image[8..16], [0; 8]);
+    assert_eq!(image[16..24], 0x1ffff0u64.to_ne_bytes());
+
+    // R_X86_64_COPY only makes sense with other files
+    assert_eq!(
+        relocate(&mut image, 0x1000, 0, [rela(0x1000, 5, 0)]),
+        Err(RelocError::Unsupported(5))
+    );
+    for offset in [0xFF8, 0x1019, 0x2000] {
+        assert_eq!(
+            relocate(&mut image, 0x1000, 0, [rela(offset, R_X86_64_RELATIVE, 0)]),
+            Err(RelocError::OutOfImage(offset))
+        );
+    }
+}
+
+#[test]
+fn test_relocate_32() {
+    let mut image = [0u8; 0x10];
+    let relocations = [
+        rela(0x1000, R_X86_64_32, 0xFFFF_FFFF),
+        rela(0x1004, R_X86_64_32S, -4),
+        rela(0x1008, R_X86_64_PC32, 0x1010),
+    ];
+    relocate(&mut image, 0x1000, 0x2000, relocations).unwrap();
+    assert_eq!(image[..4], 0xFFFF_FFFFu32.to_ne_bytes());
+    assert_eq!(image[4..8], (-4i32).to_ne_bytes());
+    // The symbol is 0, so the place at 0x3008 refers to the unrelocated 0x1010
+    assert_eq!(image[8..12], (0x1010i32 - 0x3008).to_ne_bytes());
+    assert_eq!(image[12..], [0; 4]);
+
+    for (ty, addend) in [
+        (R_X86_64_32, -1),
+        (R_X86_64_32, 0x1_0000_0000),
+        (R_X86_64_32S, 0x8000_0000),
+        (R_X86_64_32S, -0x8000_0001),
+    ] {
+        assert_eq!(
+            relocate(&mut image, 0x1000, 0, [rela(0x1000, ty, addend)]),
+            Err(RelocError::Overflow(0x1000))
+        );
+    }
+    assert_eq!(
+        relocate(
+            &mut image,
+            0x1000,
+            0x1_0000_0000,
+            [rela(0x1000, R_X86_64_PC32, 0)]
+        ),
+        Err(RelocError::Overflow(0x1000))
+    );
+    // Symbols need a symbol table
+    let mut symbolic = rela(0x1000, R_X86_64_GLOB_DAT, 0);
+    symbolic.symbol = 1;
+    assert_eq!(
+        relocate(&mut image, 0x1000, 0, [symbolic]),
+        Err(RelocError::Malformed)
+    );
+}
+
+#[test]
+fn test_dynamic() {
+    let elf = Elf::parse(&SHARED.0).unwrap();
+    let dynamic = elf.dynamic().unwrap();
+    assert_eq!(dynamic.value(DynamicTag::RelaSize), Some(4 * 24));
+    assert_eq!(dynamic.value(DynamicTag::RelaEntry), Some(24));
+    assert_eq!(dynamic.value(DynamicTag::PltRelSize), Some(2 * 24));
+    assert_eq!(dynamic.value(DynamicTag::PltRel), Some(7));
+    assert_eq!(dynamic.value(DynamicTag::Rel), None);
+    assert!(
+        dynamic
+            .clone()
+            .any(|entry| entry.tag == DynamicTag::BindNow || entry.tag == DynamicTag::Flags)
+    );
+    assert!(dynamic.clone().all(|entry| entry.tag != DynamicTag::Null));
+
+    let relocations = elf.relocations().unwrap();
+    assert_eq!(relocations.rela.count(), 4);
+    assert_eq!(relocations.rel.count(), 0);
+    let PltRelocations::Rela(plt) = relocations.plt else {
+        panic!("PLT relocations of shared.elf are RELA");
+    };
+    assert!(plt.map(|rela| rela.ty).eq([R_X86_64_JUMP_SLOT; 2]));
+
+    let elf = Elf::parse(&REL.0).unwrap();
+    let relocations = elf.relocations().unwrap();
+    assert_eq!(relocations.rela.count(), 0);
+    assert_eq!(relocations.rel.count(), 4);
+    let PltRelocations::Rel(plt) = relocations.plt else {
+        panic!("PLT relocations of rel.elf are REL");
+    };
+    assert_eq!(plt.count(), 2);
+}
+
+#[test]
+fn test_relocate_pie() {
+    let elf = Elf::parse(&PIE.0).unwrap();
+    let relocations = elf.relocations().unwrap();
+    assert!(
+        relocations
+            .rela
+            .clone()
+            .all(|rela| rela.ty == R_X86_64_RELATIVE)
+    );
+    assert_eq!(relocations.rela.count(), 8);
+
+    let image = load(&elf, BIAS).unwrap();
+    let message = read(&image, symbol(&elf, "MESSAGE"));
+    let len = read(&image, symbol(&elf, "MESSAGE") + 8);
+    assert_eq!(
+        &image[message - BIAS..message - BIAS + len],
+        b"PentOS relocation fixture"
+    );
+    assert_eq!(
+        read(&image, symbol(&elf, "COUNTER_PTR")),
+        BIAS + symbol(&elf, "COUNTER")
+    );
+    let handlers = symbol(&elf, "HANDLERS");
+    assert_eq!(read(&image, handlers), BIAS + symbol(&elf, "first"));
+    assert_eq!(read(&image, handlers + 8), BIAS + symbol(&elf, "second"));
+}
+
+#[test]
+fn test_relocate_shared() {
+    for file in [SHARED, REL] {
+        let elf = Elf::parse(&file.0).unwrap();
+        assert_shared(&elf, &load(&elf, BIAS).unwrap());
+    }
+}
+
+#[test]
+fn test_stripped_sections() {
+    for file in [SHARED, REL] {
+        let original = Elf::parse(&file.0).unwrap();
+        let mut stripped = Box::new(Aligned([0; 0x4000]));
+        let stripped = &mut stripped.0[..file.0.len()];
+        stripped.copy_from_slice(&file.0);
+        // No section header table
+        stripped[40..48].fill(0);
+        stripped[60..62].fill(0);
+        let elf = Elf::parse(stripped).unwrap();
+        assert!(elf.dynamic_symbols().is_none());
+        assert_shared(&original, &load(&elf, BIAS).unwrap());
+
+        // Without DT_HASH, the symbol table runs to the end of its segment
+        let dynamic = elf.dynamic().unwrap();
+        let index = dynamic
+            .clone()
+            .position(|entry| entry.tag == DynamicTag::Hash)
+            .unwrap();
+        let offset = elf
+            .program_header
+            .into_iter()
+            .find(|segment| segment.ty == SegmentType::Dynamic)
+            .unwrap()
+            .offset as usize
+            + index * 16;
+        stripped[offset..offset + 8].copy_from_slice(&21u64.to_ne_bytes());
+        let elf = Elf::parse(stripped).unwrap();
+        assert_eq!(elf.dynamic().unwrap().value(DynamicTag::Hash), None);
+        assert_shared(&original, &load(&elf, BIAS).unwrap());
+    }
+}
+
+#[test]
+fn test_undefined_symbol() {
+    let mut file = Box::new(Aligned(*include_bytes!("../../fixtures/shared.elf")));
+    let elf = Elf::parse(&file.0).unwrap();
+    let dynsym = elf.section_by_name(".dynsym").unwrap();
+    // Makes `missing` a strong symbol
+    let info = dynsym.offset as usize + 24 + 4;
+    assert_eq!(file.0[info], 2 << 4);
+    file.0[info] = 1 << 4;
+    let elf = Elf::parse(&file.0).unwrap();
+    assert_eq!(load(&elf, BIAS), Err(RelocError::UndefinedSymbol(1)));
+}
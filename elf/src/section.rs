@@ -0,0 +1,192 @@
+//! Section header table, see [Elf::section_header]
+
+#[cfg(test)]
+mod test;
+
+use crate::Elf;
+use crate::headers::RawSection;
+use crate::types::Offset;
+use crate::types::Word;
+use core::str;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    /// Offset of the name in the section name table, see [Elf::section_name]
+    pub name: Word,
+    pub ty: SectionType,
+    pub flags: SectionFlags,
+    /// Address in memory, 0 if the section is not loaded
+    pub addr: usize,
+    pub offset: Offset,
+    pub size: usize,
+    /// Meaning depends on the type, e.g. the string table of a symbol table
+    pub link: Word,
+    pub info: Word,
+    pub entry_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionType {
+    Null,
+    ProgBits,
+    SymbolTable,
+    StringTable,
+    Rela,
+    Hash,
+    Dynamic,
+    Note,
+    NoBits,
+    Rel,
+    DynamicSymbolTable,
+    Other(Word),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionFlags {
+    pub write: bool,
+    pub alloc: bool,
+    pub exec: bool,
+}
+
+pub struct SectionHeader<'a> {
+    raw: &'a [u8],
+    entry_size: usize,
+    pub len: usize,
+}
+
+/// Stops at the first entry outside of the file
+pub struct SectionIter<'a, 'b> {
+    section_header: &'b SectionHeader<'a>,
+    index: usize,
+}
+
+/// NUL terminated strings, referred to by their offset in the table
+#[derive(Debug, Clone, Copy)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Elf<'a> {
+    /// Contents of `section` in the file, empty for sections without any like `.bss`
+    pub fn section_data(&self, section: &Section) -> Option<&'a [u8]> {
+        if section.ty == SectionType::NoBits {
+            return Some(&[]);
+        }
+        let start = usize::try_from(section.offset).ok()?;
+        self.data.get(start..start.checked_add(section.size)?)
+    }
+    /// String table in the section at `index`
+    pub fn string_table(&self, index: usize) -> Option<StringTable<'a>> {
+        let section = self.section_header.get(index)?;
+        if section.ty != SectionType::StringTable {
+            return None;
+        }
+        Some(StringTable::new(self.section_data(&section)?))
+    }
+    pub fn section_name(&self, section: &Section) -> Option<&'a str> {
+        self.string_table(self.section_names)?.get(section.name)
+    }
+    pub fn section_by_name(&self, name: &str) -> Option<Section> {
+        let names = self.string_table(self.section_names)?;
+        self.section_header
+            .into_iter()
+            .find(|section| names.get(section.name) == Some(name))
+    }
+}
+
+impl Section {
+    pub fn parse(raw: &RawSection) -> Self {
+        Self {
+            name: raw.name,
+            ty: SectionType::parse(raw.ty),
+            flags: SectionFlags::parse(raw.flags),
+            addr: raw.addr as usize,
+            offset: raw.offset,
+            size: raw.size as usize,
+            link: raw.link,
+            info: raw.info,
+            entry_size: raw.entry_size as usize,
+        }
+    }
+}
+
+impl SectionType {
+    pub fn parse(ty: Word) -> Self {
+        match ty {
+            0 => Self::Null,
+            1 => Self::ProgBits,
+            2 => Self::SymbolTable,
+            3 => Self::StringTable,
+            4 => Self::Rela,
+            5 => Self::Hash,
+            6 => Self::Dynamic,
+            7 => Self::Note,
+            8 => Self::NoBits,
+            9 => Self::Rel,
+            11 => Self::DynamicSymbolTable,
+            ty => Self::Other(ty),
+        }
+    }
+}
+
+impl SectionFlags {
+    pub fn parse(flags: u64) -> Self {
+        Self {
+            write: flags & 1 != 0,
+            alloc: flags & 2 != 0,
+            exec: flags & 4 != 0,
+        }
+    }
+}
+
+impl<'a> SectionHeader<'a> {
+    pub fn new(raw: &'a [u8], entry_size: usize, entry_count: usize) -> Self {
+        Self {
+            raw,
+            entry_size,
+            len: entry_count,
+        }
+    }
+    pub fn get(&self, index: usize) -> Option<Section> {
+        if index >= self.len {
+            return None;
+        }
+        let offset = index.checked_mul(self.entry_size)?;
+        let raw = self.raw.get(offset..offset.checked_add(self.entry_size)?)?;
+        Some(Section::parse(RawSection::from(raw)?))
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b SectionHeader<'a> {
+    type Item = Section;
+    type IntoIter = SectionIter<'a, 'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SectionIter::<'a, 'b> {
+            section_header: self,
+            index: 0,
+        }
+    }
+}
+
+impl<'a, 'b> Iterator for SectionIter<'a, 'b> {
+    type Item = Section;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let section = self.section_header.get(self.index)?;
+        self.index += 1;
+        Some(section)
+    }
+}
+
+impl<'a> StringTable<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+    /// String at `offset`, None if it is out of the table, unterminated or not UTF-8
+    pub fn get(&self, offset: Word) -> Option<&'a str> {
+        let data = self.data.get(offset as usize..)?;
+        let len = data.iter().position(|&byte| byte == 0)?;
+        str::from_utf8(&data[..len]).ok()
+    }
+}
@@ -0,0 +1,78 @@
+use super::SectionFlags;
+use super::SectionType;
+use super::StringTable;
+use crate::Elf;
+use crate::test::SPLIT;
+use crate::test::SYMBOLS;
+use alloc::vec::Vec;
+
+#[test]
+fn test_sections() {
+    let elf = Elf::parse(&SYMBOLS.0).unwrap();
+    let names: Vec<&str> = elf
+        .section_header
+        .into_iter()
+        .map(|section| elf.section_name(&section).unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "",
+            ".text",
+            ".rodata",
+            ".data",
+            ".bss",
+            ".comment",
+            ".symtab",
+            ".strtab",
+            ".shstrtab"
+        ]
+    );
+
+    let text = elf.section_by_name(".text").unwrap();
+    assert_eq!(text.ty, SectionType::ProgBits);
+    assert_eq!(
+        text.flags,
+        SectionFlags {
+            write: false,
+            alloc: true,
+            exec: true,
+        }
+    );
+    assert_eq!((text.addr, text.size), (0x401000, 0x55));
+
+    let rodata = elf.section_by_name(".rodata").unwrap();
+    assert_eq!(
+        elf.section_data(&rodata),
+        Some(&b"PentOS loader fixture\0"[..])
+    );
+    let bss = elf.section_by_name(".bss").unwrap();
+    assert_eq!(bss.ty, SectionType::NoBits);
+    assert_eq!(elf.section_data(&bss), Some(&[][..]));
+    assert!(elf.section_by_name(".debug_info").is_none());
+    assert_eq!(
+        elf.section_header.get(6).map(|section| section.ty),
+        Some(SectionType::SymbolTable)
+    );
+    assert!(elf.section_header.get(9).is_none());
+}
+
+#[test]
+fn test_stripped_sections() {
+    let elf = Elf::parse(&SPLIT.0).unwrap();
+    assert!(elf.section_by_name(".symtab").is_none());
+    assert!(elf.section_by_name(".shstrtab").is_some());
+    // Not a string table
+    assert!(elf.string_table(1).is_none());
+}
+
+#[test]
+fn test_string_table() {
+    let strings = StringTable::new(b"\0first\0second\0\xFF\0last");
+    assert_eq!(strings.get(0), Some(""));
+    assert_eq!(strings.get(1), Some("first"));
+    assert_eq!(strings.get(9), Some("cond"));
+    assert_eq!(strings.get(14), None);
+    assert_eq!(strings.get(16), None);
+    assert_eq!(strings.get(100), None);
+}
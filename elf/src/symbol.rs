@@ -0,0 +1,210 @@
+//! Symbol tables, see [Elf::symbols]
+
+#[cfg(test)]
+mod test;
+
+use crate::Elf;
+use crate::headers::RawSymbol;
+use crate::section::Section;
+use crate::section::SectionType;
+use crate::section::StringTable;
+use crate::types::Half;
+use crate::types::UChar;
+use crate::types::Word;
+use core::mem;
+
+/// Section index of undefined symbols
+pub const SHN_UNDEF: Half = 0;
+/// Section index of symbols with an absolute value, which do not move with the file
+pub const SHN_ABS: Half = 0xFFF1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    /// Offset of the name in the string table, see [SymbolTable::name]
+    pub name: Word,
+    pub ty: SymbolType,
+    pub binding: SymbolBinding,
+    /// Index of the section the symbol is defined in, [SHN_UNDEF] if it is not
+    pub section: Half,
+    pub value: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolType {
+    NoType,
+    Object,
+    Function,
+    Section,
+    File,
+    Tls,
+    Other(UChar),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolBinding {
+    Local,
+    Global,
+    Weak,
+    Other(UChar),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolTable<'a> {
+    data: &'a [u8],
+    entry_size: usize,
+    pub strings: StringTable<'a>,
+}
+
+/// Stops at the first entry outside of the table
+pub struct SymbolIter<'a> {
+    table: SymbolTable<'a>,
+    index: usize,
+}
+
+/// Symbol an address belongs to, see [SymbolTable::lookup]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolLookup<'a> {
+    pub symbol: Symbol,
+    pub name: &'a str,
+    /// From the start of the symbol
+    pub offset: usize,
+}
+
+impl<'a> Elf<'a> {
+    /// Contents of `.symtab`, None for stripped files
+    pub fn symbols(&self) -> Option<SymbolTable<'a>> {
+        self.symbol_table(SectionType::SymbolTable)
+    }
+    /// Contents of `.dynsym`, the symbols needed at runtime
+    pub fn dynamic_symbols(&self) -> Option<SymbolTable<'a>> {
+        self.symbol_table(SectionType::DynamicSymbolTable)
+    }
+    fn symbol_table(&self, ty: SectionType) -> Option<SymbolTable<'a>> {
+        let section = self
+            .section_header
+            .into_iter()
+            .find(|section| section.ty == ty)?;
+        SymbolTable::new(self, &section)
+    }
+}
+
+impl Symbol {
+    pub fn parse(raw: &RawSymbol) -> Self {
+        Self {
+            name: raw.name,
+            ty: SymbolType::parse(raw.info & 0xF),
+            binding: SymbolBinding::parse(raw.info >> 4),
+            section: raw.section,
+            value: raw.value as usize,
+            size: raw.size as usize,
+        }
+    }
+    #[inline]
+    pub fn is_defined(&self) -> bool {
+        self.section != SHN_UNDEF
+    }
+}
+
+impl SymbolType {
+    pub fn parse(ty: UChar) -> Self {
+        match ty {
+            0 => Self::NoType,
+            1 => Self::Object,
+            2 => Self::Function,
+            3 => Self::Section,
+            4 => Self::File,
+            6 => Self::Tls,
+            ty => Self::Other(ty),
+        }
+    }
+}
+
+impl SymbolBinding {
+    pub fn parse(binding: UChar) -> Self {
+        match binding {
+            0 => Self::Local,
+            1 => Self::Global,
+            2 => Self::Weak,
+            binding => Self::Other(binding),
+        }
+    }
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Symbols of `section`, with names from the string table it links to
+    pub fn new(elf: &Elf<'a>, section: &Section) -> Option<Self> {
+        Self::from_raw(
+            elf.section_data(section)?,
+            section.entry_size,
+            elf.string_table(section.link as usize)?,
+        )
+    }
+    /// Symbols of `entry_size` bytes each in `data`, None if they are too small
+    pub fn from_raw(data: &'a [u8], entry_size: usize, strings: StringTable<'a>) -> Option<Self> {
+        if entry_size < mem::size_of::<RawSymbol>() {
+            return None;
+        }
+        Some(Self {
+            data,
+            entry_size,
+            strings,
+        })
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len() / self.entry_size
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, index: usize) -> Option<Symbol> {
+        let offset = index.checked_mul(self.entry_size)?;
+        let raw = self
+            .data
+            .get(offset..offset.checked_add(self.entry_size)?)?;
+        Some(Symbol::parse(RawSymbol::from(raw)?))
+    }
+    pub fn iter(&self) -> SymbolIter<'a> {
+        SymbolIter {
+            table: *self,
+            index: 0,
+        }
+    }
+    pub fn name(&self, symbol: &Symbol) -> Option<&'a str> {
+        self.strings.get(symbol.name)
+    }
+    /// Function or object `addr` is in, symbols without a size never match
+    pub fn lookup(&self, addr: usize) -> Option<SymbolLookup<'a>> {
+        let symbol = self.iter().find(|symbol| {
+            matches!(symbol.ty, SymbolType::Function | SymbolType::Object)
+                && symbol.is_defined()
+                && (symbol.value..symbol.value.saturating_add(symbol.size)).contains(&addr)
+        })?;
+        Some(SymbolLookup {
+            symbol,
+            name: self.name(&symbol)?,
+            offset: addr - symbol.value,
+        })
+    }
+}
+
+impl<'a> IntoIterator for &SymbolTable<'a> {
+    type Item = Symbol;
+    type IntoIter = SymbolIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Iterator for SymbolIter<'_> {
+    type Item = Symbol;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let symbol = self.table.get(self.index)?;
+        self.index += 1;
+        Some(symbol)
+    }
+}
@@ -0,0 +1,65 @@
+use super::Symbol;
+use super::SymbolBinding;
+use super::SymbolType;
+use crate::Elf;
+use crate::test::SPLIT;
+use crate::test::SYMBOLS;
+use alloc::vec::Vec;
+
+#[test]
+fn test_symbols() {
+    let elf = Elf::parse(&SYMBOLS.0).unwrap();
+    let symbols = elf.symbols().unwrap();
+    assert_eq!(symbols.len(), 9);
+    let named: Vec<(&str, SymbolType, SymbolBinding)> = symbols
+        .iter()
+        .map(|symbol| (symbols.name(&symbol).unwrap(), symbol.ty, symbol.binding))
+        .collect();
+    assert_eq!(
+        named[..6],
+        [
+            ("", SymbolType::NoType, SymbolBinding::Local),
+            ("loader.c", SymbolType::File, SymbolBinding::Local),
+            ("message", SymbolType::Object, SymbolBinding::Local),
+            ("_start", SymbolType::Function, SymbolBinding::Global),
+            ("buffer", SymbolType::Object, SymbolBinding::Global),
+            ("counter", SymbolType::Object, SymbolBinding::Global),
+        ]
+    );
+    assert_eq!(
+        symbols.get(3),
+        Some(Symbol {
+            name: symbols.get(3).unwrap().name,
+            ty: SymbolType::Function,
+            binding: SymbolBinding::Global,
+            section: 1,
+            value: 0x401000,
+            size: 0x55,
+        })
+    );
+    assert!(!symbols.get(0).unwrap().is_defined());
+    assert!(symbols.get(9).is_none());
+    // Statically linked
+    assert!(elf.dynamic_symbols().is_none());
+}
+
+#[test]
+fn test_lookup() {
+    let elf = Elf::parse(&SYMBOLS.0).unwrap();
+    let symbols = elf.symbols().unwrap();
+    let lookup = |addr| symbols.lookup(addr).map(|found| (found.name, found.offset));
+    assert_eq!(lookup(0x401000), Some(("_start", 0)));
+    assert_eq!(lookup(0x401054), Some(("_start", 0x54)));
+    assert_eq!(lookup(0x402010), Some(("message", 0x10)));
+    assert_eq!(lookup(0x403019), Some(("counter", 1)));
+    assert_eq!(lookup(0x404000), Some(("buffer", 0xFE0)));
+    // Past `_start`, and `_end` has no size
+    assert_eq!(lookup(0x401055), None);
+    assert_eq!(lookup(0x405020), None);
+}
+
+#[test]
+fn test_stripped() {
+    let elf = Elf::parse(&SPLIT.0).unwrap();
+    assert!(elf.symbols().is_none());
+}
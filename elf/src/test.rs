@@ -0,0 +1,215 @@
+//! The fixtures are built from `fixtures/loader.c` with
+//! `gcc -O2 -static -nostdlib -fno-pie -no-pie -fno-asynchronous-unwind-tables -Wl,--build-id=none`
+//! and `-Wl,-z,max-page-size=4096 -Wl,-z,noexecstack -s` for `split.elf`,
+//! `-Wl,-z,max-page-size=4096 -Wl,-z,noseparate-code -s` for `packed.elf`, `-Wl,-N -s` for `omagic.elf`,
+//! `-Wl,-z,max-page-size=4096 -Wl,-z,noexecstack` for `symbols.elf`, and for `tls.elf`
+//! built from `fixtures/tls.c`
+//!
+//! `pie.elf` is built from `fixtures/pie.rs` with the workspace toolchain, with
+//! `rustc --edition 2024 --target x86_64-unknown-none -C opt-level=2 -C panic=abort`
+//! `-C relocation-model=pie -C link-arg=-pie -C link-arg=--no-dynamic-linker`
+//! `-C link-arg=-zmax-page-size=4096 -C link-arg=--build-id=none`.
+//! `shared.elf` and `rel.elf` are built from `fixtures/reloc.c` compiled with
+//! `gcc -O2 -fPIC -fno-asynchronous-unwind-tables -fno-stack-protector -c`, then linked by
+//! the `rust-lld` of the toolchain with `-flavor gnu -shared -z max-page-size=4096`
+//! `-z noexecstack -z now --build-id=none`, and `-z rel` for `rel.elf`
+
+use crate::Elf;
+use crate::Note;
+use crate::NoteIter;
+use crate::ParseError;
+use crate::SegmentType;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// ELF files must be 8 byte aligned
+#[repr(C, align(8))]
+pub struct Aligned<T: ?Sized>(pub T);
+
+pub static SPLIT: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/split.elf"));
+pub static PACKED: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/packed.elf"));
+pub static OMAGIC: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/omagic.elf"));
+pub static SYMBOLS: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/symbols.elf"));
+pub static PIE: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/pie.elf"));
+pub static SHARED: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/shared.elf"));
+pub static REL: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/rel.elf"));
+pub static TLS: &Aligned<[u8]> = &Aligned(*include_bytes!("../fixtures/tls.elf"));
+
+/// Value of `name` in the symbol table, the link time address of most symbols, or the offset
+/// in the TLS segment for TLS ones
+pub fn symbol(elf: &Elf, name: &str) -> usize {
+    let symbols = elf.symbols().unwrap();
+    let symbol = symbols
+        .iter()
+        .find(|symbol| symbols.name(symbol) == Some(name));
+    symbol.unwrap().value
+}
+
+fn note(name: &[u8], ty: u32, desc: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::new();
+    raw.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+    raw.extend_from_slice(&(desc.len() as u32).to_ne_bytes());
+    raw.extend_from_slice(&ty.to_ne_bytes());
+    raw.extend_from_slice(name);
+    raw.resize(raw.len().next_multiple_of(4), 0);
+    raw.extend_from_slice(desc);
+    raw.resize(raw.len().next_multiple_of(4), 0);
+    raw
+}
+
+#[test]
+fn test_notes() {
+    let mut raw = note(b"PentOS\0", 1, &[1, 2, 3, 4, 5, 6]);
+    raw.extend(note(b"GNU\0", 3, &[0xAA; 20]));
+    let notes: Vec<Note> = NoteIter::new(&raw).collect();
+    assert_eq!(
+        notes,
+        vec![
+            Note {
+                name: b"PentOS",
+                ty: 1,
+                desc: &[1, 2, 3, 4, 5, 6],
+            },
+            Note {
+                name: b"GNU",
+                ty: 3,
+                desc: &[0xAA; 20],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_truncated_note() {
+    let mut raw = note(b"PentOS\0", 1, &[0; 16]);
+    let full = raw.clone();
+    raw.extend_from_slice(&full[..full.len() - 4]);
+    assert_eq!(NoteIter::new(&raw).count(), 1);
+    assert_eq!(NoteIter::new(&[0; 8]).count(), 0);
+}
+
+/// Parses the first `len` bytes of [SPLIT] after `mutate` changed them
+fn parse_mutated(len: usize, mutate: impl FnOnce(&mut [u8])) -> Result<(), ParseError> {
+    let mut raw = Box::new(Aligned([0; 0x3000]));
+    raw.0[..SPLIT.0.len()].copy_from_slice(&SPLIT.0);
+    mutate(&mut raw.0);
+    Elf::parse(&raw.0[..len]).map(|_| ())
+}
+
+fn set(raw: &mut [u8], offset: usize, value: &[u8]) {
+    raw[offset..offset + value.len()].copy_from_slice(value);
+}
+
+#[test]
+fn test_parse_header() {
+    let len = SPLIT.0.len();
+    assert_eq!(parse_mutated(len, |_| ()), Ok(()));
+    assert_eq!(parse_mutated(63, |_| ()), Err(ParseError::TruncatedHeader));
+    assert_eq!(
+        Elf::parse(&SPLIT.0[1..]).err(),
+        Some(ParseError::Misaligned)
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[1] = b'X'),
+        Err(ParseError::BadMagic)
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[4] = 1),
+        Err(ParseError::UnsupportedClass(1))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[4] = 3),
+        Err(ParseError::UnsupportedClass(3))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[5] = 2),
+        Err(ParseError::UnsupportedEncoding(2))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[6] = 2),
+        Err(ParseError::UnsupportedVersion(2))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| raw[7] = 3),
+        Err(ParseError::UnsupportedAbi(3))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 16, &9u16.to_le_bytes())),
+        Err(ParseError::UnknownType(9))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 24, &0x8000_0000_0000u64.to_le_bytes())),
+        Err(ParseError::NonCanonicalEntry(0x8000_0000_0000))
+    );
+}
+
+#[test]
+fn test_parse_tables() {
+    let len = SPLIT.0.len();
+    // Program header table past the end, or misaligned
+    for phoff in [len as u64 - 8, u64::MAX, 65] {
+        assert_eq!(
+            parse_mutated(len, |raw| set(raw, 32, &phoff.to_le_bytes())),
+            Err(ParseError::ProgramHeaderOutOfRange)
+        );
+    }
+    // Truncated
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 56, &1000u16.to_le_bytes())),
+        Err(ParseError::ProgramHeaderOutOfRange)
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 54, &32u16.to_le_bytes())),
+        Err(ParseError::ProgramHeaderEntrySize(32))
+    );
+    // Cut right before the section header table
+    let shoff = u64::from_le_bytes(SPLIT.0[40..48].try_into().unwrap()) as usize;
+    assert_eq!(
+        parse_mutated(shoff, |_| ()),
+        Err(ParseError::SectionHeaderOutOfRange)
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 58, &16u16.to_le_bytes())),
+        Err(ParseError::SectionHeaderEntrySize(16))
+    );
+    // Without sections
+    assert_eq!(parse_mutated(shoff, |raw| set(raw, 40, &[0; 8])), Ok(()));
+}
+
+#[test]
+fn test_parse_segments() {
+    let len = SPLIT.0.len();
+    // The vaddr of the first segment, at offset 0 and aligned to 4096
+    assert_eq!(
+        parse_mutated(len, |raw| set(raw, 64 + 16, &0x400010u64.to_le_bytes())),
+        Err(ParseError::MisalignedSegment(0x400010))
+    );
+    assert_eq!(
+        parse_mutated(len, |raw| set(
+            raw,
+            64 + 16,
+            &0x8000_0000_0000u64.to_le_bytes()
+        )),
+        Err(ParseError::NonCanonicalSegment(0x8000_0000_0000))
+    );
+
+    let elf = Elf::parse(&SPLIT.0).unwrap();
+    let types: Vec<SegmentType> = elf
+        .program_header
+        .into_iter()
+        .map(|segment| segment.ty)
+        .collect();
+    assert_eq!(
+        types,
+        [
+            SegmentType::Load,
+            SegmentType::Load,
+            SegmentType::Load,
+            SegmentType::Load,
+            // PT_GNU_STACK
+            SegmentType::Other(0x6474E551),
+        ]
+    );
+    assert!(elf.program_header.get(5).is_none());
+}
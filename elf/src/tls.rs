@@ -0,0 +1,96 @@
+//! Thread-local storage, see [Elf::tls]
+//!
+//! Every thread gets a block laid out following the x86_64 variant II: the TLS data is
+//! right below the thread pointer, which the FS base points to, and the thread control
+//! block starts there with a pointer to itself. Code reads `fs:0` to find the thread pointer, and
+//! accesses thread-locals at negative offsets from it.
+
+#[cfg(test)]
+mod test;
+
+use crate::Elf;
+use crate::SegmentType;
+use core::mem;
+use x64::mem::addr::Address;
+
+/// Thread control block, only its self pointer
+pub const TCB_SIZE: usize = mem::size_of::<usize>();
+
+/// Initial contents of the thread-locals, from the PT_TLS segment
+#[derive(Debug, Clone, Copy)]
+pub struct TlsTemplate<'a> {
+    /// Initialized thread-locals, `.tdata`, the rest is zeroed
+    pub data: &'a [u8],
+    /// Link time address of `data`, where relocated contents can be found in a loaded image
+    pub vaddr: usize,
+    pub mem_size: usize,
+    /// Of the thread pointer, never 0
+    pub alignment: usize,
+}
+
+impl<'a> Elf<'a> {
+    /// Template of the PT_TLS segment, None without a valid one
+    pub fn tls(&self) -> Option<TlsTemplate<'a>> {
+        let segment = self
+            .program_header
+            .into_iter()
+            .find(|segment| segment.ty == SegmentType::Tls)?;
+        if segment.file_size > *segment.mem_size
+            || segment.alignment > 1 && !segment.alignment.is_power_of_two()
+        {
+            return None;
+        }
+        Some(TlsTemplate {
+            data: self.segment_data(&segment)?,
+            vaddr: segment.vaddr.as_usize(),
+            mem_size: *segment.mem_size,
+            alignment: segment.alignment.max(1),
+        })
+    }
+}
+
+impl TlsTemplate<'_> {
+    /// No thread-locals, a block is only a thread control block
+    pub const EMPTY: Self = Self {
+        data: &[],
+        vaddr: 0,
+        mem_size: 0,
+        alignment: 1,
+    };
+
+    /// Distance from the start of a block to the thread pointer
+    #[inline]
+    pub fn tp_offset(&self) -> usize {
+        self.mem_size.next_multiple_of(self.alignment)
+    }
+    /// Of a block, TLS data and thread control block
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.tp_offset() + TCB_SIZE
+    }
+    /// Of the start of a block, for the thread pointer and the TCB to be aligned
+    #[inline]
+    pub fn block_alignment(&self) -> usize {
+        self.alignment.max(mem::align_of::<usize>())
+    }
+    /// Initializes the block in `block`, which is at `address` for the thread using it.
+    /// Returns the thread pointer, what the FS base is set to.
+    ///
+    /// `block` must be at least [TlsTemplate::block_size] long, and `address` aligned
+    /// to [TlsTemplate::block_alignment].
+    pub fn init(&self, block: &mut [u8], address: usize) -> usize {
+        assert!(block.len() >= self.block_size(), "TLS block too small");
+        assert!(
+            address % self.block_alignment() == 0,
+            "TLS block misaligned"
+        );
+        let tp_offset = self.tp_offset();
+        let thread_pointer = address + tp_offset;
+        let (tls, tcb) = block.split_at_mut(tp_offset);
+        // Padding up to the thread pointer follows the data, and is zeroed with `.tbss`
+        tls.fill(0);
+        tls[..self.data.len()].copy_from_slice(self.data);
+        tcb[..TCB_SIZE].copy_from_slice(&thread_pointer.to_ne_bytes());
+        thread_pointer
+    }
+}
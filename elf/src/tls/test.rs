@@ -0,0 +1,61 @@
+use super::TCB_SIZE;
+use super::TlsTemplate;
+use crate::Elf;
+use crate::test::SPLIT;
+use crate::test::TLS;
+use crate::test::symbol;
+use alloc::vec;
+
+#[test]
+fn test_template() {
+    let elf = Elf::parse(&TLS.0).unwrap();
+    let template = elf.tls().unwrap();
+    assert_eq!(template.data.len(), 12);
+    assert_eq!(template.mem_size, 0xB4);
+    assert_eq!(template.alignment, 64);
+    assert_eq!(template.tp_offset(), 0xC0);
+    assert_eq!(template.block_size(), 0xC0 + TCB_SIZE);
+    assert_eq!(template.block_alignment(), 64);
+
+    assert!(Elf::parse(&SPLIT.0).unwrap().tls().is_none());
+}
+
+#[test]
+fn test_init() {
+    let elf = Elf::parse(&TLS.0).unwrap();
+    let template = elf.tls().unwrap();
+    let address = 0xFFFF_8000_0000_1000;
+    let mut block = vec![0xAA; template.block_size() + 8];
+    let thread_pointer = template.init(&mut block, address);
+    assert_eq!(thread_pointer, address + 0xC0);
+    assert_eq!(block[0xC0..0xC8], thread_pointer.to_ne_bytes());
+    assert_eq!(block[0xC8..], [0xAA; 8]);
+
+    // The code of the fixture reads `counter` at fs:-0xB8
+    let counter = symbol(&elf, "counter");
+    assert_eq!(counter, 0xC0 - 0xB8);
+    assert_eq!(block[counter..counter + 4], 42u32.to_ne_bytes());
+    let buffer = symbol(&elf, "buffer");
+    assert_eq!(block[buffer..buffer + 100], [0; 100]);
+    // Alignment of `aligned` holds once the block is
+    assert_eq!((address + symbol(&elf, "aligned")) % 64, 0);
+    // Padding between the data and the thread pointer
+    assert_eq!(block[0xB4..0xC0], [0; 12]);
+}
+
+#[test]
+fn test_empty() {
+    let mut block = [0xAA; TCB_SIZE];
+    let thread_pointer = TlsTemplate::EMPTY.init(&mut block, 0x2000);
+    assert_eq!(thread_pointer, 0x2000);
+    assert_eq!(block, 0x2000usize.to_ne_bytes());
+}
+
+#[test]
+#[should_panic]
+fn test_misaligned() {
+    let elf = Elf::parse(&TLS.0).unwrap();
+    let template = elf.tls().unwrap();
+    let mut block = vec![0; template.block_size()];
+    template.init(&mut block, 0x1020);
+}
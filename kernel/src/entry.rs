@@ -1,17 +1,48 @@
+use crate::hart;
+use crate::logger;
+use crate::timeline;
+use boot_protocol::BOOT_PROTOCOL_VERSION;
+use boot_protocol::BootInfo;
+use boot_protocol::features::FeatureMask;
 use boot_protocol::kernel_meta::KernelMeta;
+use boot_protocol::kernel_meta::KernelMetaNote;
 use core::arch::asm;
-use x64::mem::addr::Address;
-use x64::mem::addr::VirtAddr;
+use log::info;
+use x64::tsc;
+
+#[used]
+#[unsafe(link_section = ".note.pentos")]
+static KERNEL_META: KernelMetaNote = KernelMetaNote::new(KernelMeta {
+    bsp_entry: Some(bsp_entry),
+    ap_entry: Some(ap_entry),
+    protocol_version: BOOT_PROTOCOL_VERSION,
+    required_features: FeatureMask::NONE,
+});
 
 #[unsafe(no_mangle)]
-extern "C" fn kernel_meta() -> KernelMeta {
-    KernelMeta {
-        bsp_entry: VirtAddr::new_panic(bsp_entry as usize),
-        ap_entry: VirtAddr::new_panic(ap_entry as usize),
+extern "C" fn bsp_entry(bootinfo: *const BootInfo) -> ! {
+    let entry_tsc = tsc::read();
+    let bootinfo = unsafe {
+        // SAFETY: The bootloader maps the whole BootInfo, whatever version it is
+        BootInfo::validate(bootinfo)
     }
-}
-
-extern "C" fn bsp_entry() {
+    .expect("Invalid BootInfo");
+    hart::init();
+    logger::init(bootinfo);
+    info!(
+        "PentOS kernel, command line \"{}\"",
+        bootinfo.cmdline.as_str()
+    );
+    info!(
+        "Kernel slid by {:#x}, physical memory mapped at {:#x}",
+        bootinfo.kaslr.kernel_slide, bootinfo.kaslr.offset_mapping
+    );
+    info!(
+        "BSP has local APIC ID {} (version {})",
+        hart::apic_id(),
+        hart::local_apic().version().version
+    );
+    timeline::log(bootinfo, entry_tsc);
     loop {
         unsafe {
             asm!(
@@ -26,7 +57,8 @@ extern "C" fn bsp_entry() {
     }
 }
 
-extern "C" fn ap_entry() {
+extern "C" fn ap_entry(_bootinfo: *const BootInfo) -> ! {
+    hart::init();
     loop {
         unsafe {
             asm!(
@@ -0,0 +1,35 @@
+//! Per-hart data
+//!
+//! State of a single hart lives in ordinary `#[thread_local]` statics. The bootloader gives
+//! every hart its own copy of the TLS segment of the kernel, and enters the kernel with the
+//! FS base on it, see [boot_protocol::topology::HartTls].
+
+use boot_protocol::LOCAL_APIC_MAPPING;
+use core::cell::Cell;
+use x64::lapic;
+use x64::lapic::LocalApicPointer;
+use x64::mem::addr::Address;
+use x64::mem::addr::VirtAddr;
+
+#[thread_local]
+static APIC_ID: Cell<Option<usize>> = Cell::new(None);
+#[thread_local]
+static LOCAL_APIC: Cell<Option<LocalApicPointer>> = Cell::new(None);
+
+/// Fills in the per-hart data of the calling hart, first thing on entry
+pub fn init() {
+    APIC_ID.set(Some(lapic::id_cpuid()));
+    LOCAL_APIC.set(Some(LocalApicPointer::new(VirtAddr::new_panic(
+        LOCAL_APIC_MAPPING,
+    ))));
+}
+
+/// Local APIC ID of the calling hart
+pub fn apic_id() -> usize {
+    APIC_ID.get().expect("Hart data not initialized")
+}
+
+/// Local APIC registers of the calling hart
+pub fn local_apic() -> LocalApicPointer {
+    LOCAL_APIC.get().expect("Hart data not initialized")
+}
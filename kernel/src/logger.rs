@@ -0,0 +1,87 @@
+use crate::timeline::BootTime;
+use boot_protocol::BootInfo;
+use boot_protocol::bootlog::BootLog;
+use console::font::PsfFont;
+use console::framebuffer::FramebufferConsole;
+use console::logger::ConsoleLogger;
+use console::serial::SerialLogger;
+use log::Log;
+use log::Record;
+use log::warn;
+use x64::serial::Uart16550;
+
+static LOGGER: KernelLogger = KernelLogger {
+    console: ConsoleLogger::new(),
+    serial: SerialLogger::new(),
+};
+
+/// Logs to the primary framebuffer and to the serial port, when there are any
+struct KernelLogger {
+    console: ConsoleLogger,
+    serial: SerialLogger,
+}
+
+pub fn init(bootinfo: &BootInfo) {
+    if let Some(framebuffer) = bootinfo.framebuffer() {
+        let console = unsafe {
+            // SAFETY: Mapped by the bootloader, and only ever used here
+            FramebufferConsole::new(framebuffer, PsfFont::default_font())
+        };
+        if let Some(console) = console {
+            LOGGER.console.set_console(console);
+        }
+    }
+    if let Some(port) = bootinfo.serial.port() {
+        let uart = unsafe {
+            // SAFETY: Found and initialized by the bootloader
+            Uart16550::new(port)
+        };
+        LOGGER.serial.set_uart(uart);
+    }
+    log::set_logger(&LOGGER).expect("Logger already set");
+    log::set_max_level(log::STATIC_MAX_LEVEL);
+    replay_boot_log(bootinfo);
+}
+
+/// Shows what the bootloader logged before the kernel sinks existed
+fn replay_boot_log(bootinfo: &BootInfo) {
+    let memory = unsafe {
+        // SAFETY: Mapped read-only by the bootloader
+        bootinfo.log.as_slice()
+    };
+    let Some(boot_log) = BootLog::open(memory) else {
+        if !memory.is_empty() {
+            warn!("Corrupt bootloader log");
+        }
+        return;
+    };
+    if boot_log.dropped() > 0 {
+        warn!("{} bootloader log records lost", boot_log.dropped());
+    }
+    for record in boot_log.records() {
+        LOGGER.log(
+            &Record::builder()
+                .level(record.level)
+                .target(record.target)
+                .args(format_args!(
+                    "[{:>14}] {}",
+                    BootTime(&bootinfo.timeline, record.timestamp),
+                    record.message
+                ))
+                .build(),
+        );
+    }
+}
+
+impl Log for KernelLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.console.log(record);
+        self.serial.log(record);
+    }
+
+    fn flush(&self) {}
+}
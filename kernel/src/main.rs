@@ -1,5 +1,9 @@
 #![no_std]
 #![no_main]
+#![feature(thread_local)]
 
 mod entry;
+mod hart;
+mod logger;
 mod panic;
+mod timeline;
@@ -0,0 +1,46 @@
+use boot_protocol::BootInfo;
+use boot_protocol::timing::BootPhase;
+use boot_protocol::timing::BootTimeline;
+use core::fmt;
+use core::fmt::Display;
+use log::info;
+
+/// Logs how long each bootloader phase took, `entry_tsc` being the TSC at kernel entry
+pub fn log(bootinfo: &BootInfo, entry_tsc: u64) {
+    let timeline = &bootinfo.timeline;
+    let entry = timeline.since_start(entry_tsc);
+    if timeline.tsc_frequency == 0 {
+        info!("Boot timeline, TSC frequency unknown");
+    } else {
+        info!(
+            "Boot timeline, TSC at {} kHz",
+            timeline.tsc_frequency / 1000
+        );
+    }
+    for phase in timeline.phases.iter() {
+        let end = if phase.phase == BootPhase::CedeControl {
+            entry
+        } else {
+            phase.end
+        };
+        info!(
+            "  {:<20} at {:>12} took {}",
+            phase.phase.name(),
+            BootTime(timeline, phase.start),
+            BootTime(timeline, end.saturating_sub(phase.start)),
+        );
+    }
+    info!("Kernel entered at {}", BootTime(timeline, entry));
+}
+
+/// TSC ticks shown in milliseconds, or as is when the TSC frequency is unknown
+pub struct BootTime<'a>(pub &'a BootTimeline, pub u64);
+
+impl Display for BootTime<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.ticks_to_us(self.1) {
+            Some(us) => write!(f, "{}.{:03} ms", us / 1000, us % 1000),
+            None => write!(f, "{} ticks", self.1),
+        }
+    }
+}
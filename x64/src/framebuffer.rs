@@ -1,18 +1,66 @@
+#[cfg(test)]
+mod test;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct PixelColor(pub u8, pub u8, pub u8);
 
-#[derive(Clone, Copy)]
+#[repr(C, u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixelMode {
     RgbRs,
     BgrRs,
+    /// Each channel is where its mask says, in a pixel of [PixelMode::bytes_per_pixel]
+    Bitmask(PixelBitmask),
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelBitmask {
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+    pub reserved: u32,
 }
 
 impl PixelColor {
+    /// Only the low [PixelMode::bytes_per_pixel] bytes are meaningful
     pub fn encode(&self, mode: PixelMode) -> u32 {
         let (b0, b1, b2, b3) = match mode {
             PixelMode::RgbRs => (self.0, self.1, self.2, 0),
             PixelMode::BgrRs => (self.2, self.1, self.0, 0),
+            PixelMode::Bitmask(mask) => {
+                return encode_channel(self.0, mask.red)
+                    | encode_channel(self.1, mask.green)
+                    | encode_channel(self.2, mask.blue);
+            }
         };
         (b3 as u32) << 24 | (b2 as u32) << 16 | (b1 as u32) << 8 | (b0 as u32)
     }
 }
+
+impl PixelMode {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelMode::RgbRs | PixelMode::BgrRs => 4,
+            PixelMode::Bitmask(mask) => {
+                let all = mask.red | mask.green | mask.blue | mask.reserved;
+                (32 - all.leading_zeros() as usize).div_ceil(8)
+            }
+        }
+    }
+}
+
+/// Scales `value` to the width of `mask`, then moves it in place
+fn encode_channel(value: u8, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let width = (mask >> shift).count_ones();
+    let scaled = if width >= 8 {
+        (value as u32) << (width - 8)
+    } else {
+        value as u32 >> (8 - width)
+    };
+    (scaled << shift) & mask
+}
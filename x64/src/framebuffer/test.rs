@@ -0,0 +1,45 @@
+use super::PixelBitmask;
+use super::PixelColor;
+use super::PixelMode;
+
+const RGB565: PixelMode = PixelMode::Bitmask(PixelBitmask {
+    red: 0xF800,
+    green: 0x07E0,
+    blue: 0x001F,
+    reserved: 0,
+});
+
+#[test]
+fn test_encode_rgb_bgr() {
+    let color = PixelColor(0x11, 0x22, 0x33);
+    assert_eq!(color.encode(PixelMode::RgbRs), 0x0033_2211);
+    assert_eq!(color.encode(PixelMode::BgrRs), 0x0011_2233);
+}
+
+#[test]
+fn test_encode_bitmask() {
+    let bgr = PixelMode::Bitmask(PixelBitmask {
+        red: 0x00FF_0000,
+        green: 0x0000_FF00,
+        blue: 0x0000_00FF,
+        reserved: 0xFF00_0000,
+    });
+    let color = PixelColor(0x11, 0x22, 0x33);
+    assert_eq!(color.encode(bgr), color.encode(PixelMode::BgrRs));
+    assert_eq!(PixelColor(0xFF, 0xFF, 0xFF).encode(RGB565), 0xFFFF);
+    assert_eq!(PixelColor(0xFF, 0, 0).encode(RGB565), 0xF800);
+    assert_eq!(PixelColor(0, 0x80, 0).encode(RGB565), 0x0400);
+}
+
+#[test]
+fn test_bytes_per_pixel() {
+    assert_eq!(PixelMode::RgbRs.bytes_per_pixel(), 4);
+    assert_eq!(RGB565.bytes_per_pixel(), 2);
+    let rgb888 = PixelMode::Bitmask(PixelBitmask {
+        red: 0xFF,
+        green: 0xFF00,
+        blue: 0xFF_0000,
+        reserved: 0,
+    });
+    assert_eq!(rgb888.bytes_per_pixel(), 3);
+}
@@ -80,6 +80,13 @@ pub enum LocalApicRegister {
     ICRHigh = 0x310,
 }
 
+impl LocalApicPointer {
+    /// `pointer` must be where the local APIC registers of the current hart are mapped
+    pub const fn new(pointer: VirtAddr) -> Self {
+        Self { pointer }
+    }
+}
+
 impl LocalApicPointer {
     pub fn read_reg32(&self, reg: LocalApicRegister) -> u32 {
         unsafe {
@@ -132,7 +139,7 @@ impl LocalApicPointer {
         let destination_mode = ipi.destination_mode as u8;
         let destination_shorthand = ipi.destination.discriminant();
 
-        let upper_dword = (destination_field as u32) >> 24;
+        let upper_dword = (destination_field as u32) << 24;
         let lower_dword = (vector as u32)
             | (delivery_mode as u32) << 8
             | (destination_mode as u32) << 11
@@ -140,8 +147,9 @@ impl LocalApicPointer {
             | (trigger_mode as u32) << 15
             | (destination_shorthand as u32) << 18;
 
+        // Writing the low dword is what sends the IPI, so it goes last
         self.write_reg32(LocalApicRegister::ICRHigh, upper_dword);
-        self.write_reg32(LocalApicRegister::ICRHigh, lower_dword);
+        self.write_reg32(LocalApicRegister::ICRLow, lower_dword);
 
         while self.read_reg32(LocalApicRegister::ICRLow) & (1 << 12) != 0 {
             hint::spin_loop();
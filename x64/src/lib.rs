@@ -16,3 +16,6 @@ pub mod lapic;
 pub mod mem;
 pub mod msr;
 pub mod prot;
+pub mod rand;
+pub mod serial;
+pub mod tsc;
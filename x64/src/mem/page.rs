@@ -57,6 +57,14 @@ impl<S: PageSize> Add<usize> for Page<S> {
     }
 }
 
+impl<S: PageSize> PartialEq for Page<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.boundary == other.boundary
+    }
+}
+
+impl<S: PageSize> Eq for Page<S> {}
+
 impl<S: PageSize> Debug for Page<S> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
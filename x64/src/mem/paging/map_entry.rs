@@ -147,6 +147,13 @@ where
         self.value & (1 << 63) != 0
     }
 
+    #[inline]
+    pub const fn target_frame(&self) -> Frame<PS::PhysicalPageSize> {
+        Frame::containing(PhysAddr::new_panic(
+            (self.value & (PS::PhysicalPageSize::MASK & PhysAddr::MASK) as u64) as usize,
+        ))
+    }
+
     #[inline]
     pub const fn pat_index(&self) -> PatIndex {
         let lower_bits = ((self.value >> 2) & 0b11) as u8;
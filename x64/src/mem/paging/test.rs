@@ -9,6 +9,7 @@ use crate::mem::page::size::Page4KiB;
 use crate::mem::page::size::PageSize;
 use crate::mem::paging::PagingMapEntry;
 use crate::mem::paging::PagingReferenceEntry;
+use crate::mem::paging::pat::PatIndex;
 
 #[test]
 fn test_map_entry_pte() {
@@ -26,6 +27,15 @@ fn test_map_entry_pde() {
     assert_eq!(*entry & Page2MiB::USE_MAP_FLAG, 1 << 7); // PS bit set
 }
 
+#[test]
+fn test_map_entry_target_frame() {
+    let frame = Frame::<Frame2MiB>::containing(PhysAddr::new_panic(0x4060_0000));
+    let entry = PagingMapEntry::<Page2MiB>::new(frame)
+        .write()
+        .with_pat_index(PatIndex::new(0b111));
+    assert_eq!(entry.target_frame().boundary(), frame.boundary());
+}
+
 #[test]
 #[should_panic]
 fn test_invalid_map_entry_pde() {
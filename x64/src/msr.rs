@@ -1,5 +1,6 @@
 pub mod apic_base;
 pub mod efer;
+pub mod fs_base;
 pub mod pat;
 
 use core::arch::asm;
@@ -1,6 +1,8 @@
 use core::ops::Deref;
 
 use super::RawMsr;
+use crate::mem::addr::Address;
+use crate::mem::addr::PhysAddr;
 
 const MSR: u32 = 0x1B;
 
@@ -18,6 +20,13 @@ impl ApicBase {
     }
 }
 
+impl ApicBase {
+    /// Physical address of the local APIC registers of the current hart
+    pub fn base(&self) -> PhysAddr {
+        PhysAddr::new_truncate(*self.raw as usize & !0xFFF)
+    }
+}
+
 impl Deref for ApicBase {
     type Target = u64;
 
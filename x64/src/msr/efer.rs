@@ -12,13 +12,13 @@ impl Efer {
     /// LME, and LMA always set
     pub fn new() -> Self {
         Self {
-            raw: RawMsr::new(0x50),
+            raw: RawMsr::new(0x500),
         }
     }
 
     pub fn read() -> Self {
         let mut raw = RawMsr::read(MSR);
-        *raw |= 0x50; // Set LME and LMA bits
+        *raw |= 0x500; // Set LME and LMA bits
         Self { raw }
     }
 
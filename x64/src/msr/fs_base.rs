@@ -0,0 +1,36 @@
+use super::RawMsr;
+use crate::mem::addr::Address;
+use crate::mem::addr::VirtAddr;
+
+const MSR: u32 = 0xC000_0100;
+
+/// Base of the FS segment, the thread pointer of the current hart
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FsBase {
+    raw: RawMsr,
+}
+
+impl FsBase {
+    pub fn new(base: VirtAddr) -> Self {
+        Self {
+            raw: RawMsr::new(base.as_usize() as u64),
+        }
+    }
+
+    pub fn read() -> Self {
+        Self {
+            raw: RawMsr::read(MSR),
+        }
+    }
+
+    pub fn write(&self) {
+        self.raw.write(MSR);
+    }
+}
+
+impl FsBase {
+    pub fn base(&self) -> VirtAddr {
+        VirtAddr::new_panic(*self.raw as usize)
+    }
+}
@@ -10,7 +10,7 @@ pub struct Pat {
     raw: RawMsr,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum MemoryType {
     Uncacheable,
     WriteCombining,
@@ -0,0 +1,34 @@
+use core::arch::asm;
+use core::arch::x86_64::__cpuid;
+
+/// Tries recommended by Intel before giving up on [rdrand]
+const RDRAND_RETRIES: usize = 10;
+
+/// Hardware random number, None if the CPU lacks RDRAND or keeps failing to provide one
+pub fn rdrand() -> Option<u64> {
+    let cpuid1 = unsafe {
+        // SAFETY: nothing to worry about
+        __cpuid(1)
+    };
+    if cpuid1.ecx & (1 << 30) == 0 {
+        return None;
+    }
+    for _ in 0..RDRAND_RETRIES {
+        let value: u64;
+        let ok: u8;
+        unsafe {
+            // SAFETY: Checked support above
+            asm!(
+                "rdrand {value}",
+                "setc {ok}",
+                value = out(reg) value,
+                ok = out(reg_byte) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 {
+            return Some(value);
+        }
+    }
+    None
+}
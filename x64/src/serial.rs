@@ -0,0 +1,176 @@
+//! 16550 compatible UART, driven through IO ports
+
+#[cfg(test)]
+mod test;
+
+use crate::io::Port;
+use core::fmt;
+use core::hint;
+
+pub const COM1: u16 = 0x3F8;
+pub const COM2: u16 = 0x2F8;
+
+/// Input clock of the baud rate generator divided by 16
+const MAX_BAUD: u32 = 115_200;
+/// How many times to poll the line status before giving up on a byte, at [MAX_BAUD]
+const TX_TIMEOUT: usize = 100_000;
+
+// Register offsets from the base port
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const DIVISOR_LOW: u16 = 0;
+const DIVISOR_HIGH: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const SCRATCH: u16 = 7;
+
+const LCR_8N1: u8 = 0b0000_0011;
+const LCR_DLAB: u8 = 1 << 7;
+/// Enable and clear both FIFOs, interrupt at 14 bytes
+const FCR_ENABLE: u8 = 0b1100_0111;
+const MCR_DTR_RTS_OUT2: u8 = 0b0000_1011;
+const MCR_LOOPBACK: u8 = 1 << 4;
+
+#[derive(Clone, Copy)]
+pub struct Uart16550 {
+    base: u16,
+}
+
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LineStatus(u8);
+
+impl Uart16550 {
+    /// # Safety
+    /// `base` must be the first IO port of a 16550 compatible UART, or of nothing at all
+    pub const unsafe fn new(base: u16) -> Self {
+        Self { base }
+    }
+
+    /// Sets up 8N1 at `baud` with FIFOs, and no interrupts.
+    /// Returns false if there is no working UART at this port, or `baud` is not achievable.
+    pub fn init(&self, baud: u32) -> bool {
+        let Some(divisor) = divisor(baud) else {
+            return false;
+        };
+        unsafe {
+            // SAFETY: The port belongs to the UART, see `new`
+            // Nothing answering there reads all ones
+            self.write(SCRATCH, 0x5A);
+            if self.read(SCRATCH) != 0x5A {
+                return false;
+            }
+            self.write(INTERRUPT_ENABLE, 0);
+            self.write(LINE_CONTROL, LCR_DLAB);
+            self.write(DIVISOR_LOW, divisor as u8);
+            self.write(DIVISOR_HIGH, (divisor >> 8) as u8);
+            self.write(LINE_CONTROL, LCR_8N1);
+            self.write(FIFO_CONTROL, FCR_ENABLE);
+
+            // Check the chip echoes in loopback mode
+            self.write(MODEM_CONTROL, MCR_LOOPBACK | MCR_DTR_RTS_OUT2);
+            self.write(DATA, 0xAE);
+            // The byte is shifted through at the programmed baud rate, slower ones wait longer
+            let arrived = poll(TX_TIMEOUT * divisor as usize, || {
+                self.line_status().data_ready()
+            });
+            let echoed = arrived && self.read(DATA) == 0xAE;
+            self.write(MODEM_CONTROL, MCR_DTR_RTS_OUT2);
+            echoed
+        }
+    }
+
+    #[inline]
+    pub fn base(&self) -> u16 {
+        self.base
+    }
+
+    pub fn line_status(&self) -> LineStatus {
+        LineStatus(unsafe {
+            // SAFETY: Reading the line status has no side effect
+            self.read(LINE_STATUS)
+        })
+    }
+
+    /// Waits for room in the transmit FIFO, drops `byte` if it never comes
+    pub fn write_byte(&self, byte: u8) {
+        if poll(TX_TIMEOUT, || self.line_status().transmitter_ready()) {
+            unsafe {
+                // SAFETY: The transmitter holding register is empty
+                self.write(DATA, byte);
+            }
+        }
+    }
+
+    pub fn try_read_byte(&self) -> Option<u8> {
+        if !self.line_status().data_ready() {
+            return None;
+        }
+        Some(unsafe {
+            // SAFETY: There is a received byte waiting
+            self.read(DATA)
+        })
+    }
+
+    #[inline]
+    unsafe fn read(&self, register: u16) -> u8 {
+        unsafe { Port::<u8>::new(self.base + register).read() }
+    }
+    #[inline]
+    unsafe fn write(&self, register: u16, value: u8) {
+        unsafe { Port::<u8>::new(self.base + register).write(value) }
+    }
+}
+
+impl LineStatus {
+    #[inline]
+    pub fn data_ready(&self) -> bool {
+        self.0 & (1 << 0) != 0
+    }
+    #[inline]
+    pub fn overrun(&self) -> bool {
+        self.0 & (1 << 1) != 0
+    }
+    #[inline]
+    pub fn transmitter_ready(&self) -> bool {
+        self.0 & (1 << 5) != 0
+    }
+    #[inline]
+    pub fn transmitter_empty(&self) -> bool {
+        self.0 & (1 << 6) != 0
+    }
+}
+
+/// Translates `\n` to `\r\n`, as terminals expect
+impl fmt::Write for Uart16550 {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Calls `ready` up to `polls` times, until it returns true
+fn poll(polls: usize, mut ready: impl FnMut() -> bool) -> bool {
+    for _ in 0..polls {
+        if ready() {
+            return true;
+        }
+        hint::spin_loop();
+    }
+    false
+}
+
+/// Baud rate generator divisor for `baud`, if it divides [MAX_BAUD]
+fn divisor(baud: u32) -> Option<u16> {
+    if baud == 0 || MAX_BAUD % baud != 0 {
+        return None;
+    }
+    (MAX_BAUD / baud).try_into().ok()
+}
@@ -0,0 +1,42 @@
+use super::LineStatus;
+use super::divisor;
+use super::poll;
+
+#[test]
+fn test_divisor() {
+    assert_eq!(divisor(115_200), Some(1));
+    assert_eq!(divisor(38_400), Some(3));
+    assert_eq!(divisor(9_600), Some(12));
+    assert_eq!(divisor(50), Some(2304));
+    assert_eq!(divisor(0), None);
+    assert_eq!(divisor(230_400), None);
+    assert_eq!(divisor(100_000), None);
+}
+
+#[test]
+fn test_line_status() {
+    let status = LineStatus(0x61);
+    assert!(status.data_ready());
+    assert!(!status.overrun());
+    assert!(status.transmitter_ready());
+    assert!(status.transmitter_empty());
+}
+
+#[test]
+fn test_poll() {
+    // Like an echo that only shows up in the line status after a few polls
+    let mut polls = 0;
+    assert!(poll(10, || {
+        polls += 1;
+        polls == 5
+    }));
+    assert_eq!(polls, 5);
+
+    let mut polls = 0;
+    assert!(!poll(4, || {
+        polls += 1;
+        polls == 5
+    }));
+    assert_eq!(polls, 4);
+    assert!(!poll(0, || true));
+}
@@ -0,0 +1,32 @@
+use core::arch::x86_64::__cpuid;
+use core::arch::x86_64::_rdtsc;
+
+/// Time stamp counter, not serialized
+#[inline]
+pub fn read() -> u64 {
+    unsafe {
+        // SAFETY: Always available in long mode
+        _rdtsc()
+    }
+}
+
+/// TSC frequency in Hz from CPUID leaf 0x15, when the CPU enumerates its crystal frequency
+pub fn cpuid_frequency() -> Option<u64> {
+    let max_basic = unsafe {
+        // SAFETY: nothing to worry about
+        __cpuid(0)
+    }
+    .eax;
+    if max_basic < 0x15 {
+        return None;
+    }
+    let leaf = unsafe {
+        // SAFETY: Checked above
+        __cpuid(0x15)
+    };
+    let (denominator, numerator, crystal) = (leaf.eax as u64, leaf.ebx as u64, leaf.ecx as u64);
+    if denominator == 0 || numerator == 0 || crystal == 0 {
+        return None;
+    }
+    Some(crystal * numerator / denominator)
+}